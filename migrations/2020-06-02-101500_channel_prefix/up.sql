@@ -0,0 +1 @@
+ALTER TABLE channels ADD COLUMN prefix VARCHAR(16) NULL;
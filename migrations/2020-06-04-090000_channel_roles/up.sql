@@ -0,0 +1,7 @@
+CREATE TABLE channel_roles (
+    id         SERIAL PRIMARY KEY,
+    channel_id INTEGER REFERENCES channels NOT NULL,
+    user_id    INTEGER REFERENCES users NOT NULL,
+    permission SMALLINT NOT NULL,
+    UNIQUE (channel_id, user_id)
+);
@@ -0,0 +1,3 @@
+ALTER TABLE users DROP COLUMN ban_reason;
+ALTER TABLE users DROP COLUMN banned_by;
+ALTER TABLE users DROP COLUMN banned_at;
@@ -0,0 +1,6 @@
+ALTER TABLE users ADD COLUMN banned_at TIMESTAMP NULL;
+ALTER TABLE users ADD COLUMN banned_by INT NULL REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE users ADD COLUMN ban_reason TEXT NULL;
+
+-- bans without banned_at are not counted anymore
+UPDATE users SET banned_at = now() AT TIME ZONE 'utc' WHERE banned_until IS NOT NULL;
@@ -0,0 +1 @@
+ALTER TABLE copypastas DROP CONSTRAINT copypastas_name_key;
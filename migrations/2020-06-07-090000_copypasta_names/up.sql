@@ -0,0 +1 @@
+ALTER TABLE copypastas ADD CONSTRAINT copypastas_name_key UNIQUE (name);
@@ -0,0 +1,2 @@
+ALTER TABLE quotes DROP COLUMN channel_id;
+ALTER TABLE quotes DROP COLUMN author_id;
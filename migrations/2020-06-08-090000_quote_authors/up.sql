@@ -0,0 +1,5 @@
+ALTER TABLE quotes ADD COLUMN author_id INT NULL REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE quotes ADD COLUMN channel_id INT NULL REFERENCES channels(id) ON DELETE SET NULL;
+
+-- link existing quotes to known chatters
+UPDATE quotes SET author_id = users.id FROM users WHERE lower(quotes.author) = users.name;
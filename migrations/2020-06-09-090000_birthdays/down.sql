@@ -0,0 +1,8 @@
+ALTER TABLE people DROP COLUMN greeted;
+
+ALTER TABLE user_settings ALTER COLUMN birthdays DROP DEFAULT;
+
+ALTER TABLE users DROP CONSTRAINT users_person_id_fkey;
+UPDATE users SET person_id = NULL;
+ALTER TABLE users ADD CONSTRAINT users_person_id_fkey
+    FOREIGN KEY (person_id) REFERENCES users(id);
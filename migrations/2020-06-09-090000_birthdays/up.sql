@@ -0,0 +1,10 @@
+-- person_id referenced users instead of people
+ALTER TABLE users DROP CONSTRAINT users_person_id_fkey;
+UPDATE users SET person_id = NULL WHERE person_id NOT IN (SELECT id FROM people);
+ALTER TABLE users ADD CONSTRAINT users_person_id_fkey
+    FOREIGN KEY (person_id) REFERENCES people(id) ON DELETE SET NULL;
+
+ALTER TABLE user_settings ALTER COLUMN birthdays SET DEFAULT false;
+
+-- the last day a birthday greeting was sent
+ALTER TABLE people ADD COLUMN greeted DATE NULL;
@@ -0,0 +1,4 @@
+ALTER TABLE user_settings DROP COLUMN language;
+ALTER TABLE user_settings DROP COLUMN mention;
+ALTER TABLE user_settings DROP COLUMN voicemail_delivery;
+ALTER TABLE user_settings DROP COLUMN timezone;
@@ -0,0 +1,5 @@
+ALTER TABLE user_settings ADD COLUMN timezone VARCHAR(64) NULL;
+ALTER TABLE user_settings ADD COLUMN voicemail_delivery VARCHAR(16) NOT NULL DEFAULT 'chat'
+    CHECK (voicemail_delivery IN ('chat', 'whisper'));
+ALTER TABLE user_settings ADD COLUMN mention BOOLEAN NOT NULL DEFAULT true;
+ALTER TABLE user_settings ADD COLUMN language VARCHAR(8) NOT NULL DEFAULT 'en';
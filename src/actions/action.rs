@@ -1,16 +1,33 @@
 use crate::{
     context::BotContext,
+    cooldown::Cooldowns,
     database::User,
-    helpers::prettify_bool,
+    helpers::{blocking, prettify_bool},
     manpages::{ChapterName, Manpage, ManpageProducer},
     message::{Message, MessageConsumer, MessageResult},
 };
 use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
 use regex::Regex;
-use std::{fmt, sync::Arc};
+use std::{fmt, future::Future, sync::Arc, time::Duration};
 
 pub type ActionFunction =
-    Box<dyn Fn(Arc<BotContext>, Message, &User) -> Result<MessageResult> + Send + Sync + 'static>;
+    Arc<dyn Fn(Arc<BotContext>, Message, &User) -> Result<MessageResult> + Send + Sync + 'static>;
+
+pub type AsyncActionFunction = Box<
+    dyn Fn(Arc<BotContext>, Message<'static>, User) -> BoxFuture<'static, Result<MessageResult>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+enum ActionKind {
+    /// Runs on the blocking thread pool. Use this for database access.
+    Blocking(ActionFunction),
+    /// Runs on the executor. Use this for network I/O.
+    Async(AsyncActionFunction),
+}
 
 // I want trait aliases PepeHands
 //pub type ActionFunctionImpl =
@@ -23,8 +40,9 @@ pub struct Action {
     about: &'static str,
     description: &'static str,
     example: Option<&'static str>,
-    command: ActionFunction,
+    command: ActionKind,
     noisy: bool,
+    cooldowns: Cooldowns,
 }
 
 impl Action {
@@ -43,6 +61,10 @@ impl Action {
     pub fn noisy(&self) -> bool {
         self.noisy
     }
+
+    pub fn cooldowns(&self) -> &Cooldowns {
+        &self.cooldowns
+    }
 }
 
 /// Shadow constructor for `ActionBuilder`
@@ -52,6 +74,7 @@ impl Action {
     }
 }
 
+#[async_trait]
 impl MessageConsumer for Action {
     fn name(&self) -> &str {
         self.name
@@ -61,17 +84,24 @@ impl MessageConsumer for Action {
         self.whitelisted
     }
 
-    fn consume(
-        &self,
+    async fn consume(
+        self: Arc<Self>,
         context: Arc<BotContext>,
         _args: Vec<String>,
-        msg: Message,
-        user: &User,
+        msg: Message<'static>,
+        user: User,
     ) -> Result<MessageResult> {
         if !self.noisy {
             info!("Executing action {}", self.name);
         }
-        (self.command)(context, msg, &user)
+
+        match &self.command {
+            ActionKind::Blocking(f) => {
+                let f = f.clone();
+                blocking(move || f(context, msg, &user)).await?
+            }
+            ActionKind::Async(f) => f(context, msg, user).await,
+        }
     }
 }
 
@@ -85,19 +115,21 @@ impl fmt::Debug for Action {
             .field("example", &self.example)
             .field("whitelisted", &self.whitelisted)
             .field("noisy", &self.noisy)
+            .field("cooldowns", &self.cooldowns)
             .finish()
     }
 }
 
 impl ManpageProducer for Action {
     fn get_manpage(&self) -> Manpage {
-        let characteristics = vec![
+        let mut characteristics = vec![
             (String::from("chainable"), prettify_bool(false).to_owned()),
             (
                 String::from("whitelisted"),
                 prettify_bool(self.whitelisted).to_owned(),
             ),
         ];
+        characteristics.extend(self.cooldowns.characteristics());
 
         Manpage::new(
             vec![self.name.to_owned()],
@@ -118,8 +150,9 @@ pub struct ActionBuilder {
     about: Option<&'static str>,
     description: Option<&'static str>,
     example: Option<&'static str>,
-    command: Option<ActionFunction>,
+    command: Option<ActionKind>,
     noisy: Option<bool>,
+    cooldowns: Cooldowns,
 }
 
 impl Into<Action> for ActionBuilder {
@@ -136,8 +169,11 @@ impl Into<Action> for ActionBuilder {
             about: self.about.unwrap_or("about missing"),
             description: self.description.unwrap_or("description missing"),
             example: self.example,
-            command: self.command.unwrap(),
+            command: self
+                .command
+                .unwrap_or_else(|| panic!("Missing function for action")),
             noisy: self.noisy.unwrap_or(false),
+            cooldowns: self.cooldowns,
         }
     }
 }
@@ -188,11 +224,42 @@ impl ActionBuilder {
         self
     }
 
+    /// Set the cooldown per user. Moderators and broadcasters are exempt.
+    pub fn user_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.user = Some(d);
+        self
+    }
+
+    /// Set the cooldown per channel. Moderators and broadcasters are exempt.
+    pub fn channel_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.channel = Some(d);
+        self
+    }
+
+    /// Set the cooldown for all channels. Moderators and broadcasters are exempt.
+    pub fn global_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.global = Some(d);
+        self
+    }
+
     pub fn command(
         mut self,
         f: impl Fn(Arc<BotContext>, Message, &User) -> Result<MessageResult> + Send + Sync + 'static,
     ) -> Self {
-        self.command = Some(Box::new(f));
+        self.command = Some(ActionKind::Blocking(Arc::new(f)));
+        self
+    }
+
+    /// Like `command` but the function returns a future. Use this if the action needs to await
+    /// network I/O.
+    pub fn async_command<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(Arc<BotContext>, Message<'static>, User) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<MessageResult>> + Send + 'static,
+    {
+        self.command = Some(ActionKind::Async(Box::new(move |context, msg, user| {
+            Box::pin(f(context, msg, user))
+        })));
         self
     }
 
@@ -0,0 +1,43 @@
+use super::prelude::*;
+use crate::{
+    birthdays::greeting,
+    database::{Person, UserSettings},
+};
+use chrono::prelude::*;
+
+pub fn action() -> Arc<Action> {
+    Action::with_name("birthday")
+        .command(move |context, _msg, user| {
+            // most users never set their birthday
+            if user.person_id.is_none() {
+                return Ok(MessageResult::None);
+            }
+
+            let conn = &context.conn();
+            let today = Local::today().naive_local();
+
+            let person = match Person::by_user(conn, user)? {
+                Some(p) if p.is_birthday(today) && !p.greeted_on(today) => p,
+                _ => return Ok(MessageResult::None),
+            };
+
+            if !UserSettings::by_user(conn, user)?.map_or(false, |s| s.birthdays) {
+                return Ok(MessageResult::None);
+            }
+
+            person.set_greeted(conn, today)?;
+
+            info!("Greeting birthday (user: {})", user.name);
+
+            Ok(MessageResult::Message(greeting(user)))
+        })
+        .about("Greet users on their birthday")
+        .description(
+            "
+Users that opted in to birthday greetings are greeted the first time they chat on their birthday.
+See `birthday` in chapter command.
+",
+        )
+        .noisy()
+        .done()
+}
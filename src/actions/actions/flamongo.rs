@@ -3,6 +3,7 @@ use rand::{
     distributions::{Distribution, Uniform},
     thread_rng,
 };
+use std::time::Duration;
 
 lazy_static! {
     static ref FLAMONGOS: Vec<&'static str> = vec![
@@ -21,6 +22,7 @@ lazy_static! {
 pub fn action() -> Arc<Action> {
     Action::with_name("flamongo")
         .regex(r"\br[io]ngo\b")
+        .channel_cooldown(Duration::from_secs(30))
         .command(move |_context, _msg, _user| {
             let range = Uniform::new(0, FLAMONGOS.len());
             let flamongo = FLAMONGOS[range.sample(&mut thread_rng())].to_owned();
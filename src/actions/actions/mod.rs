@@ -3,10 +3,16 @@ use std::sync::Arc;
 
 mod prelude;
 
+mod birthday;
 mod flamongo;
 mod test;
 mod voicemail;
 
 pub fn all() -> Vec<Arc<Action>> {
-    vec![test::action(), voicemail::action(), flamongo::action()]
+    vec![
+        test::action(),
+        voicemail::action(),
+        flamongo::action(),
+        birthday::action(),
+    ]
 }
@@ -1,5 +1,5 @@
 use super::prelude::*;
-use crate::database::Voicemail;
+use crate::database::{UserSettings, Voicemail};
 
 pub fn action() -> Arc<Action> {
     Action::with_name("voicemail")
@@ -15,9 +15,13 @@ pub fn action() -> Arc<Action> {
 
             trace!("Found {} voicemails", voicemails.len());
 
-            Ok(MessageResult::Message(Voicemail::format_vec(
-                conn, voicemails,
-            )?))
+            let text = Voicemail::format_vec(conn, voicemails)?;
+
+            Ok(if UserSettings::of(conn, user)?.whisper_voicemails() {
+                MessageResult::Whisper(text)
+            } else {
+                MessageResult::Message(text)
+            })
         })
         .noisy()
         .done()
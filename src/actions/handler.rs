@@ -1,35 +1,51 @@
 use super::action::Action;
 use crate::{
     context::BotContext,
-    database::User,
+    cooldown::CooldownTracker,
+    database::{Channel, User, UserSettings},
     handler::{Handler, SimpleHandler, Twitch},
-    message::{Message, MessageConsumer, MessageResult},
+    helpers::{blocking, Permission},
+    message::{Message, MessageConsumer},
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 use thiserror::Error;
-use twitchchat::messages::Privmsg;
 
 #[derive(Debug, Error)]
 pub enum ActionHandlerError {
     #[error("Could not execute action (name: {0})")]
     ExecuteAction(String),
 
-    #[error("Could not send privmsg")]
-    SendPrivmsg,
+    #[error("Could not get channel")]
+    GetChannel,
+
+    #[error("Channel not found")]
+    ChannelNotFound,
+
+    #[error("Could not get action filter")]
+    GetFilter,
+
+    #[error("Could not get user settings")]
+    GetSettings,
 }
 
 pub struct ActionHandler {
     actions: Vec<Arc<Action>>,
 
+    cooldowns: CooldownTracker,
+
     context: Arc<BotContext>,
 }
 
 impl ActionHandler {
     /// Create a new ActionHandler
     pub fn new(context: Arc<BotContext>, actions: Vec<Arc<Action>>) -> Self {
-        Self { context, actions }
+        Self {
+            context,
+            actions,
+            cooldowns: CooldownTracker::new(),
+        }
     }
 }
 
@@ -47,59 +63,118 @@ impl SimpleHandler for ActionHandler {
 
 #[async_trait]
 impl Twitch for ActionHandler {
-    async fn handle(&self, msg: Arc<Privmsg<'_>>, user: &User) -> Result<()> {
-        let message = msg.data.trim().replace("\u{e0000}", ""); // remove chatterino chars
+    async fn handle(self: Arc<Self>, msg: Message<'static>, user: &User) -> Result<()> {
+        // actions only react to chat
+        if msg.is_whisper() {
+            return Ok(());
+        }
+
+        let (actions, mention) = {
+            let this = self.clone();
+            let (msg, user) = (msg.clone(), user.clone());
+
+            // database queries are blocking
+            blocking(move || -> Result<_> {
+                let actions = this.select(&msg, &user)?;
+
+                // most chat lines match no action, nothing is sent for them
+                let mention = !actions.is_empty()
+                    && UserSettings::of(&this.context.conn(), &user)
+                        .context(ActionHandlerError::GetSettings)?
+                        .mention;
+
+                Ok((actions, mention))
+            })
+            .await??
+        };
+
+        for action in actions {
+            trace!("Executing action");
+
+            let name = action.name().to_owned();
+            let result = action
+                .consume(self.context.clone(), Vec::new(), msg.clone(), user.clone())
+                .await
+                .context(ActionHandlerError::ExecuteAction(name))?;
+
+            self.context
+                .twitchbot()
+                .respond(&msg, user, result, mention);
+        }
+
+        Ok(())
+    }
+}
+
+impl ActionHandler {
+    /// Select the actions that should be executed for `msg` and start their cooldowns.
+    fn select(&self, msg: &Message<'static>, user: &User) -> Result<Vec<Arc<Action>>> {
+        let message = msg.text().trim().replace("\u{e0000}", ""); // remove chatterino chars
 
-        let actions = self.actions.iter().filter(|&act| act.is_match(&message));
-        let mut writer = self.context.twitchbot().writer();
+        let mut actions = self
+            .actions
+            .iter()
+            .filter(|&act| act.is_match(&message))
+            .peekable();
+
+        if actions.peek().is_none() {
+            return Ok(Vec::new());
+        }
+
+        let channel = Channel::by_name(&self.context.conn(), msg.channel().trim_start_matches('#'))
+            .context(ActionHandlerError::GetChannel)?
+            .context(ActionHandlerError::ChannelNotFound)?;
+
+        if channel.paused {
+            trace!("Channel is paused (channel: {})", msg.channel());
+            return Ok(Vec::new());
+        }
+
+        // moderators and broadcasters are exempt from cooldowns
+        let exempt = Permission::from_user(&self.context.conn(), msg, user)
+            .map(|p| p >= Permission::Moderator)
+            .unwrap_or(false);
+
+        let mut selected = Vec::new();
 
         for action in actions {
             // do not write to log on every message
             debug!("Found matching action {:?}", action);
 
-            if action.whitelisted() {
+            // whitelisted actions are disabled unless enabled explicitly
+            let enabled = channel
+                .action_filter(&self.context.conn(), action.name())
+                .context(ActionHandlerError::GetFilter)?
+                .unwrap_or(!action.whitelisted());
+
+            if !enabled {
                 debug!(
-                    "Action is not whitelisted in this channel (name: {}, channel: {})",
+                    "Action is not enabled in this channel (name: {}, channel: {})",
                     action.name(),
-                    msg.channel
+                    msg.channel()
                 );
-                return Ok(());
+                continue;
             }
 
-            trace!("Executing action");
+            if !exempt {
+                let uses = [(action.name(), action.cooldowns())];
+
+                if let Some((_, remaining)) =
+                    self.cooldowns
+                        .try_start(&uses, msg.channel(), user.id, Instant::now())
+                {
+                    debug!(
+                        "Action is on cooldown (name: {}, remaining: {:?})",
+                        action.name(),
+                        remaining
+                    );
+                    continue;
+                }
+            }
 
-            match action
-                .consume(
-                    self.context.clone(),
-                    Vec::new(),
-                    Message::TwitchPrivmsg(msg.clone()),
-                    user,
-                )
-                .context(ActionHandlerError::ExecuteAction(action.name().to_owned()))?
-            {
-                MessageResult::None => Ok(()),
-                MessageResult::Reply(m) => writer
-                    .privmsg(
-                        &msg.channel,
-                        format!("{}, {}", user.display_name_or_name(), m).as_str(),
-                    )
-                    .await
-                    .context(ActionHandlerError::SendPrivmsg),
-                MessageResult::Message(m) => writer
-                    .privmsg(&msg.channel, &m)
-                    .await
-                    .context(ActionHandlerError::SendPrivmsg),
-                MessageResult::Error(m) => writer
-                    .privmsg(&msg.channel, format!("Error: {}", m))
-                    .await
-                    .context(ActionHandlerError::SendPrivmsg),
-                MessageResult::MissingArgument(a) => writer
-                    .privmsg(&msg.channel, format!("Missing argument `{}`", a))
-                    .await
-                    .context(ActionHandlerError::SendPrivmsg),
-            }?;
+            selected.push(action.clone());
         }
 
-        Ok(())
+        Ok(selected)
     }
 }
@@ -10,12 +10,17 @@ extern crate diesel_migrations;
 
 use chb4::{
     actions::{self, ActionHandler},
+    birthdays,
     commands::{self, CommandHandler},
+    console::{self, Console},
     context::BotContext,
-    database::{self, Channel, Voicemail},
-    handler::Twitch,
+    database::{Channel, Voicemail},
+    events::EventLogger,
+    handler::{Twitch, TwitchEvents},
+    helpers::blocking,
     manpages, TwitchBot,
 };
+use chrono::{NaiveTime, Utc};
 use config::{Config, Environment, File, FileFormat};
 use diesel::r2d2::{ConnectionManager, Pool};
 use flexi_logger::Logger;
@@ -43,8 +48,48 @@ pub enum Error {
     #[snafu(display("Building R2D2 Pool: {}", source))]
     BuildR2D2Pool { source: r2d2::Error },
 
-    #[snafu(display("Getting enabled channels: {}", source))]
-    GetEnabledChannels { source: database::channel::Error },
+    #[snafu(display("Running console: {}", source))]
+    RunConsole { source: console::Error },
+}
+
+/// Options for the local console (`--console`)
+#[derive(Debug)]
+struct ConsoleOptions {
+    user: String,
+    channel: Option<String>,
+    moderator: bool,
+}
+
+impl ConsoleOptions {
+    /// Parse `--console [--user NAME] [--channel NAME] [--moderator]`. Returns `None` if the
+    /// console is not requested.
+    fn from_args(mut args: impl Iterator<Item = String>) -> Option<Self> {
+        let mut console = false;
+        let mut options = Self {
+            user: String::from("console"),
+            channel: None,
+            moderator: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--console" => console = true,
+                "--moderator" => options.moderator = true,
+                "--user" => match args.next() {
+                    Some(user) => options.user = user,
+                    None => warn!("Missing value for --user"),
+                },
+                "--channel" => options.channel = args.next(),
+                _ => warn!("Unknown argument: {}", arg),
+            }
+        }
+
+        if console {
+            Some(options)
+        } else {
+            None
+        }
+    }
 }
 
 /// The main is currently full of bloat. The plan is to move everything into their own modules
@@ -63,6 +108,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     info!("Starting CHB4 {} ({})", version, git_hash);
 
+    let console_options = ConsoleOptions::from_args(env::args().skip(1));
+
     // Load config
     let mut config = Config::new();
     config
@@ -90,6 +137,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             target: "environment",
         })?;
 
+    config
+        .set_default("twitch.prefix", "~")
+        .context(GetConfigEntry)?;
+
     info!("Loaded Config");
 
     info!("Connecting to Database");
@@ -103,7 +154,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     debug!("Ran database migrations");
 
-    let (twitchbot, runner) = TwitchBot::new();
+    let (twitchbot, runner) = match console_options {
+        Some(_) => TwitchBot::console(),
+        None => TwitchBot::new(),
+    };
 
     let action_index = actions::all();
     let command_index = commands::all();
@@ -130,44 +184,93 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         Arc::new(command_handler) as Arc<dyn Twitch>,
     ];
 
-    let channels = {
+    let event_handlers = [Arc::new(EventLogger) as Arc<dyn TwitchEvents>];
+
+    {
         let bot_channel = context.bot_name();
-        let conn = &context.conn();
 
         // ensure the bot channel is in the database
-        let _ = Channel::join(conn, &bot_channel); // ignore result
-
-        Channel::all_enabled(conn).context(GetEnabledChannels)?
-    };
-
-    debug!("Found {} channels to join", channels.len());
+        let _ = Channel::join(&context.conn(), &bot_channel); // ignore result
+    }
 
     // schedule voicemails
     {
         let context = context.clone();
         tokio::task::spawn(async move {
             trace!("Scheduling old voicemails");
-            let conn = &context.conn();
-            let voicemails = match Voicemail::active_scheduled(conn) {
-                Ok(v) => match v {
+            let voicemails = {
+                let context = context.clone();
+                blocking(move || Voicemail::active_scheduled(&context.conn())).await
+            };
+            let voicemails = match voicemails {
+                Ok(Ok(v)) => match v {
                     Some(v) => v,
                     None => {
                         info!("no scheduled voicemails found");
                         return;
                     }
                 },
-                Err(e) => {
+                Ok(Err(e)) => {
                     error!("Could not get voicemails for scheduling: {}", e);
                     return;
                 }
+                Err(e) => {
+                    error!("Could not run blocking task: {}", e);
+                    return;
+                }
             };
 
+            // overdue voicemails were due while the bot was offline and are delivered right away
+            let now = Utc::now().naive_utc();
+            let overdue = voicemails
+                .iter()
+                .filter(|v| v.scheduled.map_or(false, |s| s <= now))
+                .count();
+
+            info!(
+                "Scheduling {} voicemails ({} overdue)",
+                voicemails.len(),
+                overdue
+            );
+
             for voicemail in voicemails {
-                context.scheduler().schedule(voicemail).unwrap();
+                let id = voicemail.id;
+                if let Err(err) = context.scheduler().schedule(voicemail) {
+                    error!("Could not schedule voicemail (id: {}): {}", id, err);
+                }
             }
         });
     }
 
+    // greet birthdays in their channel at a fixed time
+    if let Ok(at) = context.config().get_str("birthdays.greet_at") {
+        match NaiveTime::parse_from_str(&at, "%H:%M") {
+            Ok(at) => {
+                tokio::task::spawn(birthdays::run(context.clone(), at));
+            }
+            Err(err) => warn!("Invalid birthdays.greet_at (value: {}): {}", at, err),
+        }
+    }
+
+    if let Some(options) = console_options {
+        let channel = options.channel.unwrap_or_else(|| context.bot_name());
+        let console = Console::new(
+            context.clone(),
+            Arc::new(twitch_handlers),
+            &options.user,
+            &channel,
+        )
+        .moderator(options.moderator);
+
+        // the scheduler never stops, so stop when stdin is closed
+        tokio::select! {
+            result = console.run() => result.context(RunConsole)?,
+            _ = BotContext::run_scheduler(context.clone()) => {},
+        }
+
+        return Ok(());
+    }
+
     // get credentials from config
     let name = context.config().get_str("twitch.name").unwrap();
     let token = context.config().get_str("twitch.token").unwrap();
@@ -186,7 +289,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             name,
             token,
             Arc::new(twitch_handlers),
-            channels
+            Arc::new(event_handlers),
         ),
     );
 
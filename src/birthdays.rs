@@ -0,0 +1,104 @@
+//! Birthday greetings
+//!
+//! Opted in users are greeted once on their birthday. The `birthday` action greets them the first
+//! time they chat. If `birthdays.greet_at` is configured, users with a channel are greeted there
+//! at that time.
+use crate::{
+    context::BotContext,
+    database::{self, Channel, Person, User},
+    helpers::blocking,
+};
+use chrono::prelude::*;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::sync::Arc;
+use tokio::time::delay_for;
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Running blocking task: {}", source))]
+    Blocking { source: tokio::task::JoinError },
+
+    #[snafu(display("Getting birthdays: {}", source))]
+    GetBirthdays { source: database::person::Error },
+
+    #[snafu(display("Getting channel: {}", source))]
+    GetChannel { source: database::channel::Error },
+
+    #[snafu(display("Channel not found (id: {})", id))]
+    ChannelNotFound { id: i32 },
+
+    #[snafu(display("Getting channel name: {}", source))]
+    GetChannelName { source: database::channel::Error },
+
+    #[snafu(display("Setting greeted: {}", source))]
+    SetGreeted { source: database::person::Error },
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+pub fn greeting(user: &User) -> String {
+    format!(
+        "Happy birthday {}! FeelsBirthdayMan",
+        user.display_name_or_name()
+    )
+}
+
+/// Greet users in their channel every day at `at` (local time).
+pub async fn run(context: Arc<BotContext>, at: NaiveTime) {
+    debug!("starting birthday loop (at: {})", at);
+
+    loop {
+        let now = Local::now().naive_local();
+        let mut next = now.date().and_time(at);
+        if next <= now {
+            next += chrono::Duration::days(1);
+        }
+
+        // the difference is always positive
+        delay_for((next - now).to_std().unwrap_or_default()).await;
+
+        let context = context.clone();
+        if let Err(err) = blocking(move || greet_all(&context))
+            .await
+            .context(Blocking)
+            .and_then(|result| result)
+        {
+            error!("Could not greet birthdays: {}", err);
+        }
+    }
+}
+
+fn greet_all(context: &BotContext) -> Result<()> {
+    let conn = &context.conn();
+    let today = Local::today().naive_local();
+
+    for (person, user) in Person::birthdays(conn).context(GetBirthdays)? {
+        if !person.is_birthday(today) || person.greeted_on(today) {
+            continue;
+        }
+
+        // users without a channel are greeted when they chat
+        let id = match user.channel_id {
+            Some(id) => id,
+            None => continue,
+        };
+
+        let channel = Channel::by_id(conn, id)
+            .context(GetChannel)?
+            .context(ChannelNotFound { id })?;
+
+        if !channel.enabled || channel.paused {
+            continue;
+        }
+
+        let name = channel.name(conn).context(GetChannelName)?;
+
+        person.set_greeted(conn, today).context(SetGreeted)?;
+
+        info!("Greeting birthday (user: {}, channel: {})", user.name, name);
+
+        context.twitchbot().say(&name, &greeting(&user));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,558 @@
+//! Declarative arguments for commands
+//!
+//! A command describes its sub-commands, positional arguments and flags with `Arguments`. The
+//! arguments are parsed before the command is executed and the manpage generates the usage from the
+//! same description.
+use crate::voicemail::parse_duration;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    ops::Deref,
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ArgumentError {
+    #[error("Missing argument: {0}")]
+    Missing(&'static str),
+
+    #[error("{value} is not a valid {kind} for {name}")]
+    Invalid {
+        name: &'static str,
+        value: String,
+        kind: ArgType,
+    },
+
+    #[error("Unknown sub-command: {0}")]
+    UnknownSubcommand(String),
+
+    #[error("Unexpected argument: {0}")]
+    Unexpected(String),
+}
+
+/// The type of a positional argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgType {
+    /// Any word.
+    Text,
+    /// A whole number like `42`.
+    Integer,
+    /// A user name. A leading `@` is removed and the name is lowercased.
+    User,
+    /// A channel name. A leading `#` is removed and the name is lowercased.
+    Channel,
+    /// A duration like `1h`, `2d12h` or `2 days 12h`. It can span several words.
+    Duration,
+    /// One of the listed words.
+    OneOf(&'static [&'static str]),
+}
+
+impl ArgType {
+    /// Check `value` and normalize it.
+    fn check(self, value: &str) -> Option<String> {
+        match self {
+            Self::Text => Some(value.to_owned()),
+            Self::Integer => value.parse::<i64>().ok().map(|_| value.to_owned()),
+            Self::User => Some(value.trim_start_matches('@').to_lowercase()),
+            Self::Channel => Some(value.trim_start_matches('#').to_lowercase()),
+            Self::Duration => parse_duration(value).map(|_| value.to_owned()),
+            Self::OneOf(words) => words
+                .iter()
+                .find(|w| w.eq_ignore_ascii_case(value))
+                .map(|w| (*w).to_owned()),
+        }
+    }
+}
+
+impl fmt::Display for ArgType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => f.write_str("text"),
+            Self::Integer => f.write_str("number"),
+            Self::User => f.write_str("user"),
+            Self::Channel => f.write_str("channel"),
+            Self::Duration => f.write_str("duration"),
+            Self::OneOf(words) => write!(f, "choice of {}", words.join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Positional {
+    name: &'static str,
+    kind: ArgType,
+    required: bool,
+    /// Takes all remaining words.
+    rest: bool,
+}
+
+impl fmt::Display for Positional {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = self.name.to_uppercase();
+        let name = if self.rest { name + "..." } else { name };
+
+        if self.required {
+            f.write_str(&name)
+        } else {
+            write!(f, "[{}]", name)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Subcommand {
+    pub(crate) name: &'static str,
+    aliases: Vec<&'static str>,
+    about: &'static str,
+    arguments: Arguments,
+}
+
+/// The arguments a command accepts.
+///
+/// Optional arguments are only filled if there are enough words left for the required ones. If an
+/// optional argument is not `Text` and the word does not match its type the argument is skipped.
+#[derive(Debug, Clone, Default)]
+pub struct Arguments {
+    positionals: Vec<Positional>,
+    flags: Vec<&'static str>,
+    subcommands: Vec<Subcommand>,
+    default_subcommand: Option<&'static str>,
+}
+
+/// Builder functions
+impl Arguments {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn required(self, name: &'static str, kind: ArgType) -> Self {
+        self.positional(name, kind, true, false)
+    }
+
+    pub fn optional(self, name: &'static str, kind: ArgType) -> Self {
+        self.positional(name, kind, false, false)
+    }
+
+    /// All remaining words joined by a space. At least one word is needed.
+    pub fn rest(self, name: &'static str) -> Self {
+        self.positional(name, ArgType::Text, true, true)
+    }
+
+    /// All remaining words joined by a space.
+    pub fn optional_rest(self, name: &'static str) -> Self {
+        self.positional(name, ArgType::Text, false, true)
+    }
+
+    fn positional(mut self, name: &'static str, kind: ArgType, required: bool, rest: bool) -> Self {
+        if self.positionals.last().map_or(false, |p| p.rest) {
+            warn!(
+                "Argument after a rest argument is never filled (name: {})",
+                name
+            );
+        }
+
+        self.positionals.push(Positional {
+            name,
+            kind,
+            required,
+            rest,
+        });
+        self
+    }
+
+    /// A flag like `--all`. Flags can be anywhere in the arguments.
+    pub fn flag(mut self, name: &'static str) -> Self {
+        self.flags.push(name);
+        self
+    }
+
+    /// A sub-command with its own arguments. It is selected by the first word.
+    pub fn subcommand(mut self, name: &'static str, about: &'static str, arguments: Self) -> Self {
+        self.subcommands.push(Subcommand {
+            name,
+            aliases: Vec::new(),
+            about,
+            arguments,
+        });
+        self
+    }
+
+    /// Use the sub-command `name` if no arguments are given.
+    pub fn default_subcommand(mut self, name: &'static str) -> Self {
+        self.default_subcommand = Some(name);
+        self
+    }
+
+    /// Add an alias for the sub-command `name`.
+    pub fn alias(mut self, name: &'static str, alias: &'static str) -> Self {
+        match self.subcommands.iter_mut().find(|s| s.name == name) {
+            Some(sub) => sub.aliases.push(alias),
+            None => warn!("Alias for unknown sub-command (name: {})", name),
+        }
+        self
+    }
+}
+
+impl Arguments {
+    /// Parse `args` according to the description.
+    pub fn parse(&self, args: Vec<String>) -> Result<Matches, ArgumentError> {
+        let mut matches = Matches::from(args.clone());
+        let words: Vec<&str> = args.iter().map(String::as_str).collect();
+
+        self.parse_into(&words, &mut matches)?;
+
+        Ok(matches)
+    }
+
+    fn parse_into(&self, args: &[&str], matches: &mut Matches) -> Result<(), ArgumentError> {
+        let mut words = Vec::new();
+
+        for arg in args {
+            match arg
+                .strip_prefix("--")
+                .and_then(|f| self.flags.iter().find(|name| **name == f))
+            {
+                Some(name) => {
+                    matches.flags.insert(name);
+                }
+                None => words.push(*arg),
+            }
+        }
+
+        if let Some(sub) = words.first().and_then(|w| self.find_subcommand(w)) {
+            matches.subcommand = Some(sub.name);
+            return sub.arguments.parse_into(&words[1..], matches);
+        }
+
+        if let Some(sub) = self
+            .default_subcommand
+            .filter(|_| words.is_empty())
+            .and_then(|name| self.find_subcommand(name))
+        {
+            matches.subcommand = Some(sub.name);
+            return sub.arguments.parse_into(&[], matches);
+        }
+
+        // without own positionals a sub-command is needed
+        if !self.subcommands.is_empty() && self.positionals.is_empty() {
+            return Err(match words.first() {
+                Some(w) => ArgumentError::UnknownSubcommand((*w).to_owned()),
+                None => ArgumentError::Missing("sub-command"),
+            });
+        }
+
+        let required = self.positionals.iter().filter(|p| p.required).count();
+        let mut spare = words.len().saturating_sub(required);
+        let mut pos = 0;
+
+        for p in &self.positionals {
+            let left = &words[pos..];
+
+            let value = if p.rest {
+                pos = words.len();
+                Some(left.join(" ")).filter(|s| !s.is_empty())
+            } else if p.kind == ArgType::Duration && (p.required || spare > 0) {
+                // a duration can span several words like `2 days 12h`, take the longest one
+                let max = if p.required { spare + 1 } else { spare };
+                match duration_len(&left[..max.min(left.len())]) {
+                    Some(n) => {
+                        spare -= if p.required { n - 1 } else { n };
+                        pos += n;
+                        Some(left[..n].join(" "))
+                    }
+                    None if p.required => left.first().map(|w| {
+                        pos += 1;
+                        (*w).to_owned()
+                    }),
+                    None => None,
+                }
+            } else if p.required {
+                left.first().map(|w| {
+                    pos += 1;
+                    (*w).to_owned()
+                })
+            } else if spare > 0 {
+                match left.first() {
+                    Some(w) if p.kind == ArgType::Text || p.kind.check(w).is_some() => {
+                        spare -= 1;
+                        pos += 1;
+                        Some((*w).to_owned())
+                    }
+                    _ => None,
+                }
+            } else {
+                None
+            };
+
+            match value {
+                Some(value) => {
+                    let checked = p.kind.check(&value).ok_or_else(|| ArgumentError::Invalid {
+                        name: p.name,
+                        value: value.clone(),
+                        kind: p.kind,
+                    })?;
+                    matches.values.insert(p.name, checked);
+                }
+                None if p.required => return Err(ArgumentError::Missing(p.name)),
+                None => {}
+            }
+        }
+
+        match words.get(pos) {
+            Some(w) => Err(ArgumentError::Unexpected((*w).to_owned())),
+            None => Ok(()),
+        }
+    }
+
+    /// Find the sub-command `word` by its name or one of its aliases.
+    pub(crate) fn find_subcommand(&self, word: &str) -> Option<&Subcommand> {
+        self.subcommands
+            .iter()
+            .find(|s| s.name == word || s.aliases.contains(&word))
+    }
+
+    /// Render the usage of the command `name`. Every line is one way to call the command.
+    pub fn usage(&self, name: &str) -> Vec<String> {
+        self.usage_with_about(name, None)
+    }
+
+    fn usage_with_about(&self, name: &str, about: Option<&str>) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if self.subcommands.is_empty() || !self.positionals.is_empty() {
+            let mut words = vec![name.to_owned()];
+            words.extend(self.flags.iter().map(|f| format!("[--{}]", f)));
+            words.extend(self.positionals.iter().map(Positional::to_string));
+
+            let line = format!("`{}`", words.join(" "));
+            lines.push(match about {
+                Some(about) => format!("{} -- {}", line, about),
+                None => line,
+            });
+        }
+
+        for sub in &self.subcommands {
+            let mut names = vec![sub.name];
+            names.extend(&sub.aliases);
+
+            lines.extend(
+                sub.arguments
+                    .usage_with_about(&format!("{} {}", name, names.join("|")), Some(sub.about)),
+            );
+        }
+
+        lines
+    }
+}
+
+/// The number of words at the start of `words` that form the longest duration.
+fn duration_len(words: &[&str]) -> Option<usize> {
+    (1..=words.len())
+        .rev()
+        .find(|&n| parse_duration(&words[..n].join(" ")).is_some())
+}
+
+/// Parsed arguments. Dereferences to the raw words.
+#[derive(Debug, Clone, Default)]
+pub struct Matches {
+    raw: Vec<String>,
+    subcommand: Option<&'static str>,
+    values: HashMap<&'static str, String>,
+    flags: HashSet<&'static str>,
+}
+
+impl Matches {
+    /// The name of the selected sub-command. Aliases are resolved.
+    pub fn subcommand(&self) -> Option<&'static str> {
+        self.subcommand
+    }
+
+    /// Get the value of the argument `name`.
+    pub fn text(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// Get the value of the argument `name` parsed as `T`.
+    pub fn value<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.text(name).and_then(|v| v.parse().ok())
+    }
+
+    /// Get the value of the duration argument `name`.
+    pub fn duration(&self, name: &str) -> Option<chrono::Duration> {
+        self.text(name).and_then(parse_duration)
+    }
+
+    /// Check if the flag `name` is set.
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.contains(name)
+    }
+
+    pub fn into_vec(self) -> Vec<String> {
+        self.raw
+    }
+}
+
+impl From<Vec<String>> for Matches {
+    fn from(raw: Vec<String>) -> Self {
+        Self {
+            raw,
+            ..Self::default()
+        }
+    }
+}
+
+impl Deref for Matches {
+    type Target = Vec<String>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.raw
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        let spec = Arguments::new()
+            .subcommand(
+                "add",
+                "add a quote",
+                Arguments::new().flag("silent").rest("quote"),
+            )
+            .subcommand(
+                "remove",
+                "remove a quote",
+                Arguments::new().required("id", ArgType::Integer),
+            )
+            .alias("remove", "delete")
+            .required("id", ArgType::Integer);
+
+        let m = spec.parse(args("add --silent \"hi\" - me")).unwrap();
+        assert_eq!(m.subcommand(), Some("add"));
+        assert_eq!(m.text("quote"), Some("\"hi\" - me"));
+        assert!(m.flag("silent"));
+
+        let m = spec.parse(args("delete 4")).unwrap();
+        assert_eq!(m.subcommand(), Some("remove"));
+        assert_eq!(m.value::<i32>("id"), Some(4));
+
+        let m = spec.parse(args("7")).unwrap();
+        assert_eq!(m.subcommand(), None);
+        assert_eq!(m.value::<i32>("id"), Some(7));
+
+        assert_eq!(
+            spec.parse(args("remove")).unwrap_err(),
+            ArgumentError::Missing("id")
+        );
+        assert_eq!(
+            spec.parse(args("random")).unwrap_err(),
+            ArgumentError::Invalid {
+                name: "id",
+                value: String::from("random"),
+                kind: ArgType::Integer
+            }
+        );
+        assert_eq!(
+            spec.parse(args("7 8")).unwrap_err(),
+            ArgumentError::Unexpected(String::from("8"))
+        );
+
+        let spec = Arguments::new()
+            .subcommand("show", "show settings", Arguments::new())
+            .subcommand(
+                "set",
+                "change a setting",
+                Arguments::new().required("value", ArgType::Text),
+            )
+            .default_subcommand("show");
+
+        assert_eq!(spec.parse(args("")).unwrap().subcommand(), Some("show"));
+        assert_eq!(spec.parse(args("set x")).unwrap().subcommand(), Some("set"));
+        assert_eq!(
+            spec.parse(args("x")).unwrap_err(),
+            ArgumentError::UnknownSubcommand(String::from("x"))
+        );
+    }
+
+    #[test]
+    fn test_parse_optional_arguments() {
+        let spec = Arguments::new()
+            .optional("chapter", ArgType::OneOf(&["command", "action"]))
+            .required("name", ArgType::Text)
+            .optional("channel", ArgType::Channel);
+
+        let m = spec.parse(args("ping")).unwrap();
+        assert_eq!(m.text("chapter"), None);
+        assert_eq!(m.text("name"), Some("ping"));
+
+        let m = spec.parse(args("ping #Forsen")).unwrap();
+        assert_eq!(m.text("chapter"), None);
+        assert_eq!(m.text("name"), Some("ping"));
+        assert_eq!(m.text("channel"), Some("forsen"));
+
+        let m = spec.parse(args("Action ping")).unwrap();
+        assert_eq!(m.text("chapter"), Some("action"));
+        assert_eq!(m.text("name"), Some("ping"));
+
+        let spec = Arguments::new()
+            .required("user", ArgType::User)
+            .optional("duration", ArgType::Duration)
+            .optional_rest("reason");
+
+        let m = spec.parse(args("@Someone 1h spam")).unwrap();
+        assert_eq!(m.text("user"), Some("someone"));
+        assert_eq!(m.duration("duration"), Some(chrono::Duration::hours(1)));
+        assert_eq!(m.text("reason"), Some("spam"));
+
+        let m = spec.parse(args("someone being rude")).unwrap();
+        assert_eq!(m.duration("duration"), None);
+        assert_eq!(m.text("reason"), Some("being rude"));
+
+        // the longest duration is used
+        let m = spec.parse(args("someone 2 days 12h spam")).unwrap();
+        assert_eq!(m.duration("duration"), Some(chrono::Duration::hours(60)));
+        assert_eq!(m.text("reason"), Some("spam"));
+
+        let m = spec.parse(args("someone 2 days")).unwrap();
+        assert_eq!(m.duration("duration"), Some(chrono::Duration::days(2)));
+        assert_eq!(m.text("reason"), None);
+
+        let m = spec.parse(args("someone 2 spammers")).unwrap();
+        assert_eq!(m.duration("duration"), None);
+        assert_eq!(m.text("reason"), Some("2 spammers"));
+    }
+
+    #[test]
+    fn test_usage() {
+        let spec = Arguments::new()
+            .subcommand("stop", "stop the bot", Arguments::new())
+            .subcommand(
+                "leave",
+                "leave a channel",
+                Arguments::new().required("channel", ArgType::Channel),
+            )
+            .alias("leave", "part");
+
+        assert_eq!(
+            spec.usage("admin"),
+            vec![
+                "`admin stop` -- stop the bot",
+                "`admin leave|part CHANNEL` -- leave a channel"
+            ]
+        );
+
+        let spec = Arguments::new()
+            .flag("all")
+            .optional("zone", ArgType::Text)
+            .optional_rest("text");
+
+        assert_eq!(spec.usage("time"), vec!["`time [--all] [ZONE] [TEXT...]`"]);
+    }
+}
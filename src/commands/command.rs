@@ -1,20 +1,43 @@
+use super::arguments::{ArgumentError, Arguments, Matches};
 use crate::{
     context::BotContext,
+    cooldown::Cooldowns,
     database::User,
-    helpers::prettify_bool,
+    helpers::{blocking, prettify_bool, Permission, Requirement},
     manpages::{ChapterName, Manpage, ManpageProducer},
     message::{Message, MessageConsumer, MessageResult},
 };
 use anyhow::Result;
-use std::{fmt, sync::Arc};
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use std::{fmt, future::Future, sync::Arc, time::Duration};
 
-pub type CommandFunction = Box<
-    dyn Fn(Arc<BotContext>, Vec<String>, Message, &User) -> Result<MessageResult>
+pub type CommandFunction = Arc<
+    dyn Fn(Arc<BotContext>, Matches, Message, &User) -> Result<MessageResult>
         + Send
         + Sync
         + 'static,
 >;
 
+pub type AsyncCommandFunction = Box<
+    dyn Fn(
+            Arc<BotContext>,
+            Matches,
+            Message<'static>,
+            User,
+        ) -> BoxFuture<'static, Result<MessageResult>>
+        + Send
+        + Sync
+        + 'static,
+>;
+
+enum CommandKind {
+    /// Runs on the blocking thread pool. Use this for database access.
+    Blocking(CommandFunction),
+    /// Runs on the executor. Use this for network I/O.
+    Async(AsyncCommandFunction),
+}
+
 // I want trait aliases PepeHands
 // pub type CommandFunctionImpl =
 //     impl Fn(Vec<String>, Message, &User) -> Result + Send + Sync + 'static;
@@ -27,7 +50,11 @@ pub struct Command {
     about: &'static str,
     description: &'static str,
     example: Option<&'static str>,
-    command: CommandFunction,
+    cooldowns: Cooldowns,
+    requirement: Requirement,
+    subcommand_requirements: Vec<(&'static str, Requirement)>,
+    arguments: Option<Arguments>,
+    command: CommandKind,
 }
 
 impl Command {
@@ -35,10 +62,32 @@ impl Command {
         self.aliases.clone()
     }
 
-    #[allow(dead_code)]
     pub fn chainable(&self) -> bool {
         self.chainable
     }
+
+    pub fn cooldowns(&self) -> &Cooldowns {
+        &self.cooldowns
+    }
+
+    /// Get what a user needs to run the command with `args`. A sub-command given as the first
+    /// argument can override the requirement of the command. Aliases of sub-commands get the
+    /// requirement of the sub-command.
+    pub fn requirement(&self, args: &[String]) -> Requirement {
+        args.get(0)
+            .map(|word| {
+                self.arguments
+                    .as_ref()
+                    .and_then(|arguments| arguments.find_subcommand(word))
+                    .map_or(word.as_str(), |sub| sub.name)
+            })
+            .and_then(|sub| {
+                self.subcommand_requirements
+                    .iter()
+                    .find(|(name, _)| *name == sub)
+            })
+            .map_or(self.requirement, |(_, r)| *r)
+    }
 }
 
 /// Shadow constructor for `CommandBuilder`
@@ -48,6 +97,7 @@ impl Command {
     }
 }
 
+#[async_trait]
 impl MessageConsumer for Command {
     fn name(&self) -> &str {
         self.name
@@ -57,15 +107,33 @@ impl MessageConsumer for Command {
         self.whitelisted
     }
 
-    fn consume(
-        &self,
+    async fn consume(
+        self: Arc<Self>,
         context: Arc<BotContext>,
         args: Vec<String>,
-        msg: Message,
-        user: &User,
+        msg: Message<'static>,
+        user: User,
     ) -> Result<MessageResult> {
         info!("Executing command {} with args {:?}", self.name, args);
-        (self.command)(context, args, msg, &user)
+
+        let args = match &self.arguments {
+            Some(arguments) => match arguments.parse(args) {
+                Ok(matches) => matches,
+                Err(ArgumentError::Missing(name)) => {
+                    return Ok(MessageResult::MissingArgument(name))
+                }
+                Err(err) => return Ok(MessageResult::Error(err.to_string())),
+            },
+            None => Matches::from(args),
+        };
+
+        match &self.command {
+            CommandKind::Blocking(f) => {
+                let f = f.clone();
+                blocking(move || f(context, args, msg, &user)).await?
+            }
+            CommandKind::Async(f) => f(context, args, msg, user).await,
+        }
     }
 }
 
@@ -79,6 +147,10 @@ impl fmt::Debug for Command {
             .field("about", &self.about)
             .field("description", &self.description)
             .field("example", &self.example)
+            .field("cooldowns", &self.cooldowns)
+            .field("requirement", &self.requirement)
+            .field("subcommand_requirements", &self.subcommand_requirements)
+            .field("arguments", &self.arguments)
             .finish()
     }
 }
@@ -93,7 +165,7 @@ impl ManpageProducer for Command {
             .map(|x| (*x).to_string())
             .collect::<Vec<String>>();
 
-        let characteristics = vec![
+        let mut characteristics = vec![
             (
                 String::from("chainable"),
                 prettify_bool(self.chainable).to_owned(),
@@ -103,15 +175,30 @@ impl ManpageProducer for Command {
                 prettify_bool(self.whitelisted).to_owned(),
             ),
         ];
+        match self.requirement {
+            Requirement::Permission(p) if p <= Permission::User => {}
+            r => characteristics.push((String::from("permission"), r.to_string())),
+        }
+        characteristics.extend(
+            self.subcommand_requirements
+                .iter()
+                .map(|(name, r)| (format!("permission ({})", name), r.to_string())),
+        );
+        characteristics.extend(self.cooldowns.characteristics());
 
-        Manpage::new(
+        let manpage = Manpage::new(
             names,
             ChapterName::Command,
             self.about.to_owned(),
             self.description.to_owned(),
             self.example.map(|s| s.to_owned()),
             characteristics,
-        )
+        );
+
+        match &self.arguments {
+            Some(arguments) => manpage.usage(arguments.usage(self.name)),
+            None => manpage,
+        }
     }
 }
 
@@ -124,7 +211,11 @@ pub struct CommandBuilder {
     about: Option<&'static str>,
     description: Option<&'static str>,
     example: Option<&'static str>,
-    command: Option<CommandFunction>,
+    cooldowns: Cooldowns,
+    requirement: Option<Requirement>,
+    subcommand_requirements: Vec<(&'static str, Requirement)>,
+    arguments: Option<Arguments>,
+    command: Option<CommandKind>,
 }
 
 impl Into<Command> for CommandBuilder {
@@ -139,7 +230,15 @@ impl Into<Command> for CommandBuilder {
             about: self.about.unwrap_or("about missing"),
             description: self.description.unwrap_or("description missing"),
             example: self.example,
-            command: self.command.unwrap(),
+            cooldowns: self.cooldowns,
+            requirement: self
+                .requirement
+                .unwrap_or(Requirement::Permission(Permission::Unknown)),
+            subcommand_requirements: self.subcommand_requirements,
+            arguments: self.arguments,
+            command: self
+                .command
+                .unwrap_or_else(|| panic!("Missing function for command")),
         }
     }
 }
@@ -181,6 +280,7 @@ impl CommandBuilder {
         self
     }
 
+    /// Allow the command to be part of a pipeline like `~color | ~test`.
     pub fn chainable(mut self) -> Self {
         self.chainable = Some(true);
         self
@@ -207,14 +307,76 @@ impl CommandBuilder {
         self
     }
 
+    /// Set the cooldown per user. Moderators and broadcasters are exempt.
+    pub fn user_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.user = Some(d);
+        self
+    }
+
+    /// Set the cooldown per channel. Moderators and broadcasters are exempt.
+    pub fn channel_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.channel = Some(d);
+        self
+    }
+
+    /// Set the cooldown for all channels. Moderators and broadcasters are exempt.
+    pub fn global_cooldown(mut self, d: Duration) -> Self {
+        self.cooldowns.global = Some(d);
+        self
+    }
+
+    /// Set the permission a user needs to run the command. Everyone can run it by default.
+    pub fn permission(self, p: Permission) -> Self {
+        self.requirement(Requirement::Permission(p))
+    }
+
+    /// Like `permission` but for requirements that are not a plain permission level, like
+    /// `Requirement::ChannelBroadcasterOrOwner`.
+    pub fn requirement(mut self, r: Requirement) -> Self {
+        self.requirement = Some(r);
+        self
+    }
+
+    /// Override the permission for a sub-command. The sub-command is the first argument.
+    pub fn subcommand_permission(self, name: &'static str, p: Permission) -> Self {
+        self.subcommand_requirement(name, Requirement::Permission(p))
+    }
+
+    /// Override the requirement for a sub-command. The sub-command is the first argument and also
+    /// matches the aliases declared in the arguments.
+    pub fn subcommand_requirement(mut self, name: &'static str, r: Requirement) -> Self {
+        self.subcommand_requirements.push((name, r));
+        self
+    }
+
+    /// Declare the arguments of the command. They are parsed before the command is executed and
+    /// the usage in the manpage is generated from them.
+    pub fn arguments(mut self, arguments: Arguments) -> Self {
+        self.arguments = Some(arguments);
+        self
+    }
+
     pub fn command(
         mut self,
-        f: impl Fn(Arc<BotContext>, Vec<String>, Message, &User) -> Result<MessageResult>
+        f: impl Fn(Arc<BotContext>, Matches, Message, &User) -> Result<MessageResult>
             + Send
             + Sync
             + 'static,
     ) -> Self {
-        self.command = Some(Box::new(f));
+        self.command = Some(CommandKind::Blocking(Arc::new(f)));
+        self
+    }
+
+    /// Like `command` but the function returns a future. Use this if the command needs to await
+    /// network I/O.
+    pub fn async_command<F, Fut>(mut self, f: F) -> Self
+    where
+        F: Fn(Arc<BotContext>, Matches, Message<'static>, User) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<MessageResult>> + Send + 'static,
+    {
+        self.command = Some(CommandKind::Async(Box::new(
+            move |context, args, msg, user| Box::pin(f(context, args, msg, user)),
+        )));
         self
     }
 
@@ -222,3 +384,36 @@ impl CommandBuilder {
         Arc::new(Command { ..self.into() })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subcommand_requirement() {
+        let cmd = Command::with_name("quote")
+            .subcommand_permission("remove", Permission::Friend)
+            .arguments(
+                Arguments::new()
+                    .subcommand("list", "list quotes", Arguments::new())
+                    .subcommand("remove", "remove a quote", Arguments::new())
+                    .alias("remove", "delete"),
+            )
+            .command(|_context, _args, _msg, _user| Ok(MessageResult::None))
+            .done();
+
+        let args = |s: &str| vec![s.to_owned()];
+        let friend = Requirement::Permission(Permission::Friend);
+
+        assert_eq!(cmd.requirement(&args("remove")), friend);
+        assert_eq!(cmd.requirement(&args("delete")), friend);
+        assert_eq!(
+            cmd.requirement(&args("list")),
+            Requirement::Permission(Permission::Unknown)
+        );
+        assert_eq!(
+            cmd.requirement(&[]),
+            Requirement::Permission(Permission::Unknown)
+        );
+    }
+}
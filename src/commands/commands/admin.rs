@@ -1,28 +1,84 @@
 use super::prelude::*;
-use crate::database::Channel;
-
-use futures_executor::block_on;
+use crate::{
+    commands::arguments::Matches,
+    database::{Channel, User},
+    helpers::blocking,
+    manpages::ChapterName,
+};
+use chrono::prelude::*;
+use humantime::format_duration;
+use std::time::Duration;
 
 pub fn command() -> Arc<Command> {
     Command::with_name("admin")
-        .command(move |context, args, msg, user| {
-            let permission = Permission::from_user(msg, user).unwrap();
-
-            if permission != Permission::Owner {
-                debug!(
-                    "Permission not high enough (is: {:?}, needed: {:?})",
-                    permission,
-                    Permission::Owner
-                );
-                return Ok(MessageResult::None);
-            }
-
-            match args.get(0).map(String::as_str) {
-                Some("stop") => stop(context.clone()),
-                Some("leave") => leave(context.clone(), args[1..].to_vec()),
-                Some("join") => join(context.clone(), args[1..].to_vec()),
-                Some(_) => Ok(MessageResult::Message("Unknown sub-command".into())),
-                None => Ok(MessageResult::MissingArgument("Missing sub-command")),
+        .permission(Permission::Owner)
+        .arguments(
+            Arguments::new()
+                .subcommand("stop", "stop the bot", Arguments::new())
+                .subcommand(
+                    "leave",
+                    "leave a channel",
+                    Arguments::new().required("channel", ArgType::Channel),
+                )
+                .subcommand(
+                    "join",
+                    "join a channel",
+                    Arguments::new().required("channel", ArgType::Channel),
+                )
+                .subcommand(
+                    "enable",
+                    "enable a command or action in a channel",
+                    filter_arguments(),
+                )
+                .subcommand(
+                    "disable",
+                    "disable a command or action in a channel",
+                    filter_arguments(),
+                )
+                .subcommand(
+                    "grant",
+                    "give a user a role, globally or in a channel",
+                    Arguments::new()
+                        .required("user", ArgType::User)
+                        .required("role", ArgType::Text)
+                        .optional("channel", ArgType::Channel),
+                )
+                .subcommand(
+                    "revoke",
+                    "remove the global role or the role in a channel of a user",
+                    Arguments::new()
+                        .required("user", ArgType::User)
+                        .optional("channel", ArgType::Channel),
+                )
+                .subcommand(
+                    "ban",
+                    "ignore everything a user says",
+                    Arguments::new()
+                        .required("user", ArgType::User)
+                        .optional("permanent", ArgType::OneOf(&["permanent"]))
+                        .optional("duration", ArgType::Duration)
+                        .optional_rest("reason"),
+                )
+                .subcommand(
+                    "unban",
+                    "lift the ban of a user",
+                    Arguments::new().required("user", ArgType::User),
+                )
+                .subcommand("bans", "list all banned users", Arguments::new()),
+        )
+        .async_command(|context, args, msg, user| async move {
+            match args.subcommand() {
+                Some("stop") => stop(context),
+                Some("leave") => leave(context, args).await,
+                Some("join") => join(context, args).await,
+                Some("enable") => blocking(move || filter(context, msg, args, true)).await?,
+                Some("disable") => blocking(move || filter(context, msg, args, false)).await?,
+                Some("grant") => blocking(move || grant(context, args)).await?,
+                Some("revoke") => blocking(move || revoke(context, args)).await?,
+                Some("ban") => blocking(move || ban(context, user, args)).await?,
+                Some("unban") => blocking(move || unban(context, args)).await?,
+                Some("bans") => blocking(move || bans(context)).await?,
+                _ => Ok(MessageResult::MissingArgument("sub-command")),
             }
         })
         .about("Various commands to manage the bot.")
@@ -30,66 +86,397 @@ pub fn command() -> Arc<Command> {
             r#"
 NOTE: This is a owner only command!
 
-=== USAGE
+If `CHANNEL` is omitted the current channel is used for `enable` and `disable`.
+Without `CHANNEL` `grant` and `revoke` change the global role.
 
-```
-admin SUBCOMMAND
-```
+`DURATION` is a duration like `1h`, `2d12h` or `2 days 12h`. Use `permanent` instead of a duration
+to ban a user permanently. Temporary bans expire on their own.
 
-.SUBCOMMAND
-* `stop` -- stop the bot
-* `leave CHANNEL` -- leave a channel
-* `join CHANNEL` -- join a channel
+`ROLE` is one of `user`, `subscriber`, `vip`, `moderator`, `broadcaster`, `friend` or `owner`.
+`friend` and `owner` are global roles, `subscriber`, `vip`, `moderator` and `broadcaster` need a
+`CHANNEL`.
+Twitch badges are used too: the highest of the global role, the role in the channel and the badges
+counts.
+`CHAPTER` is either `command` or `action` and is only needed if both a command and an action with
+`NAME` exist.
 "#,
         )
         .done()
 }
 
+/// `[CHAPTER] NAME [CHANNEL]`
+fn filter_arguments() -> Arguments {
+    Arguments::new()
+        .optional("chapter", ArgType::OneOf(&["command", "action"]))
+        .required("name", ArgType::Text)
+        .optional("channel", ArgType::Channel)
+}
+
 fn stop(context: Arc<BotContext>) -> Result<MessageResult> {
     warn!("Stopping bot by command!");
 
     // stop the chat client
-    block_on(async {
-        info!("Stopping chat client");
-        context.twitchbot().stop()
-    });
+    info!("Stopping chat client");
+    context.twitchbot().stop();
 
     info!("Stopping process");
     std::process::exit(0);
 }
 
-fn leave(context: Arc<BotContext>, args: Vec<String>) -> Result<MessageResult> {
-    let name = match args.get(0) {
-        Some(c) => c,
-        None => return Ok(MessageResult::MissingArgument("channel")),
-    };
+async fn leave(context: Arc<BotContext>, args: Matches) -> Result<MessageResult> {
+    let name = args.text("channel").unwrap_or_default().to_owned();
 
-    let conn = &context.conn();
-    let channel = Channel::by_name(conn, name)?;
+    let channel = {
+        let context = context.clone();
+        let name = name.clone();
+        blocking(move || Channel::by_name(&context.conn(), &name)).await??
+    };
 
     let channel = match channel {
         Some(c) => c,
         None => return Ok(MessageResult::Error(format!("I am no in channel {}", name))),
     };
 
-    context.twitchbot().part(name)?;
+    context.twitchbot().part(&name).await?;
 
-    channel.leave(conn)?;
+    blocking(move || channel.leave(&context.conn())).await??;
 
     Ok(MessageResult::Message(format!("I lef channel {}", name)))
 }
 
-fn join(context: Arc<BotContext>, args: Vec<String>) -> Result<MessageResult> {
-    let channel = match args.get(0) {
+async fn join(context: Arc<BotContext>, args: Matches) -> Result<MessageResult> {
+    let channel = args.text("channel").unwrap_or_default().to_owned();
+
+    context.twitchbot().join(&channel).await?;
+
+    {
+        let channel = channel.clone();
+        blocking(move || Channel::join(&context.conn(), &channel)).await??;
+    }
+
+    Ok(MessageResult::Message(format!("Joined {}", channel)))
+}
+
+fn filter(
+    context: Arc<BotContext>,
+    msg: Message,
+    args: Matches,
+    enable: bool,
+) -> Result<MessageResult> {
+    // the chapter is optional and only needed if the name is ambiguous
+    let chapter = args
+        .text("chapter")
+        .map(|c| ChapterName::from(c.to_owned()));
+    let name = args.text("name").unwrap_or_default();
+
+    let chapter = match chapter {
         Some(c) => c,
-        None => return Ok(MessageResult::MissingArgument("channel")),
+        None => {
+            let command = context.whatis(Some(ChapterName::Command), name.to_owned());
+            let action = context.whatis(Some(ChapterName::Action), name.to_owned());
+
+            match (command, action) {
+                (Some(_), Some(_)) => {
+                    return Ok(MessageResult::Error(format!(
+                        "{} is both a command and an action. Please specify the chapter",
+                        name
+                    )))
+                }
+                (Some(_), None) => ChapterName::Command,
+                (None, Some(_)) => ChapterName::Action,
+                (None, None) => {
+                    return Ok(MessageResult::Error(format!(
+                        "No command or action named {} found",
+                        name
+                    )))
+                }
+            }
+        }
+    };
+
+    // resolve aliases to the real name
+    let name = match context.whatis(Some(chapter.clone()), name.to_owned()) {
+        Some(page) => page.name().to_owned(),
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "No {} named {} found",
+                chapter, name
+            )))
+        }
+    };
+
+    let channel_name = match args.text("channel") {
+        Some(c) => c.to_owned(),
+        None => msg.channel().trim_start_matches('#').to_owned(),
     };
 
     let conn = &context.conn();
+    let channel = match Channel::by_name(conn, &channel_name)? {
+        Some(c) => c,
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "I am not in channel {}",
+                channel_name
+            )))
+        }
+    };
 
-    context.twitchbot().join(channel)?;
+    match chapter {
+        ChapterName::Action => channel.set_action_filter(conn, &name, enable)?,
+        _ => channel.set_command_filter(conn, &name, enable)?,
+    }
 
-    Channel::join(conn, &channel)?;
+    Ok(MessageResult::Message(format!(
+        "{} {} {} in {}",
+        if enable { "Enabled" } else { "Disabled" },
+        chapter,
+        name,
+        channel_name
+    )))
+}
 
-    Ok(MessageResult::Message(format!("Joined {}", channel)))
+fn grant(context: Arc<BotContext>, args: Matches) -> Result<MessageResult> {
+    let name = args.text("user").unwrap_or_default();
+    let role = args.text("role").unwrap_or_default();
+
+    let permission = match Permission::from_name(role) {
+        Some(p) => p,
+        None => return Ok(MessageResult::Error(format!("Unknown role {}", role))),
+    };
+
+    let conn = &context.conn();
+    let user = match User::by_name(conn, name)? {
+        Some(u) => u,
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "I have never seen {} before",
+                name
+            )))
+        }
+    };
+
+    match args.text("channel") {
+        Some(channel_name) => {
+            let channel = match Channel::by_name(conn, channel_name)? {
+                Some(c) => c,
+                None => {
+                    return Ok(MessageResult::Error(format!(
+                        "I am not in channel {}",
+                        channel_name
+                    )))
+                }
+            };
+
+            if permission.is_global_role() {
+                return Ok(MessageResult::Error(format!(
+                    "{} is a global role, leave out the channel",
+                    permission
+                )));
+            }
+
+            channel.set_role(conn, user.id, Some(permission.into()))?;
+
+            info!(
+                "Granted role (user: {}, role: {}, channel: {})",
+                name, permission, channel_name
+            );
+
+            Ok(MessageResult::Message(format!(
+                "{} is now {} in {}",
+                name, permission, channel_name
+            )))
+        }
+        None => {
+            if permission.is_channel_role() {
+                return Ok(MessageResult::Error(format!(
+                    "{} is a channel role, add the channel",
+                    permission
+                )));
+            }
+
+            user.set_permission(conn, permission.into())?;
+
+            info!("Granted global role (user: {}, role: {})", name, permission);
+
+            Ok(MessageResult::Message(format!(
+                "{} is now {}",
+                name, permission
+            )))
+        }
+    }
+}
+
+fn revoke(context: Arc<BotContext>, args: Matches) -> Result<MessageResult> {
+    let name = args.text("user").unwrap_or_default();
+
+    let conn = &context.conn();
+    let user = match User::by_name(conn, name)? {
+        Some(u) => u,
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "I have never seen {} before",
+                name
+            )))
+        }
+    };
+
+    match args.text("channel") {
+        Some(channel_name) => {
+            let channel = match Channel::by_name(conn, channel_name)? {
+                Some(c) => c,
+                None => {
+                    return Ok(MessageResult::Error(format!(
+                        "I am not in channel {}",
+                        channel_name
+                    )))
+                }
+            };
+
+            channel.set_role(conn, user.id, None)?;
+
+            info!("Revoked role (user: {}, channel: {})", name, channel_name);
+
+            Ok(MessageResult::Message(format!(
+                "Removed the role of {} in {}",
+                name, channel_name
+            )))
+        }
+        None => {
+            user.set_permission(conn, Permission::Unknown.into())?;
+
+            info!("Revoked global role (user: {})", name);
+
+            Ok(MessageResult::Message(format!(
+                "Removed the global role of {}",
+                name
+            )))
+        }
+    }
+}
+
+fn ban(context: Arc<BotContext>, banner: User, args: Matches) -> Result<MessageResult> {
+    let name = args.text("user").unwrap_or_default();
+    let duration = args.duration("duration");
+    let reason = args.text("reason");
+    let now = Local::now();
+
+    let until = match (args.text("permanent"), duration) {
+        (Some(_), None) => None,
+        (None, Some(d)) => match now.naive_utc().checked_add_signed(d) {
+            Some(until) => Some(until),
+            None => {
+                return Ok(MessageResult::Error(String::from(
+                    "That duration is too long, use permanent instead",
+                )))
+            }
+        },
+        (Some(_), Some(_)) => {
+            return Ok(MessageResult::Error(String::from(
+                "A ban is either permanent or has a duration",
+            )))
+        }
+        (None, None) => return Ok(MessageResult::MissingArgument("duration")),
+    };
+
+    let conn = &context.conn();
+    let user = match User::by_name(conn, name)? {
+        Some(u) => u,
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "I have never seen {} before",
+                name
+            )))
+        }
+    };
+
+    if user.id == banner.id {
+        return Ok(MessageResult::Error(String::from(
+            "You cannot ban yourself",
+        )));
+    }
+
+    if Permission::from(user.permission) == Permission::Owner {
+        return Ok(MessageResult::Error(String::from(
+            "Owners cannot be banned",
+        )));
+    }
+
+    user.ban(conn, &banner, until.as_ref(), reason, &now)?;
+
+    info!(
+        "Banned user (user: {}, banner: {}, until: {:?}, reason: {:?})",
+        name, banner.name, until, reason
+    );
+
+    Ok(MessageResult::Message(match duration {
+        Some(d) => format!(
+            "Banned {} for {}",
+            name,
+            format_duration(Duration::from_secs(d.num_seconds() as u64))
+        ),
+        None => format!("Banned {} permanently", name),
+    }))
+}
+
+fn unban(context: Arc<BotContext>, args: Matches) -> Result<MessageResult> {
+    let name = args.text("user").unwrap_or_default();
+
+    let conn = &context.conn();
+    let user = match User::by_name(conn, name)? {
+        Some(u) => u,
+        None => {
+            return Ok(MessageResult::Error(format!(
+                "I have never seen {} before",
+                name
+            )))
+        }
+    };
+
+    if !user.banned(&Local::now()) {
+        return Ok(MessageResult::Message(format!("{} is not banned", name)));
+    }
+
+    user.unban(conn)?;
+
+    info!("Unbanned user (user: {})", name);
+
+    Ok(MessageResult::Message(format!("Unbanned {}", name)))
+}
+
+fn bans(context: Arc<BotContext>) -> Result<MessageResult> {
+    let conn = &context.conn();
+    let users = User::bans(conn, &Local::now())?;
+
+    if users.is_empty() {
+        return Ok(MessageResult::Message(String::from("Nobody is banned")));
+    }
+
+    let mut bans = Vec::new();
+
+    for user in users {
+        let until = match user.banned_until {
+            Some(until) => format!("until {} UTC", until.format("%Y-%m-%d %H:%M")),
+            None => String::from("permanently"),
+        };
+
+        let banner = match user.banned_by {
+            Some(id) => User::by_id(conn, id)?.map(|u| u.name),
+            None => None,
+        };
+
+        let mut ban = format!("{} {}", user.name, until);
+        if let Some(banner) = banner {
+            ban.push_str(&format!(" by {}", banner));
+        }
+        if let Some(reason) = user.ban_reason {
+            ban.push_str(&format!(" ({})", reason));
+        }
+
+        bans.push(ban);
+    }
+
+    Ok(MessageResult::Message(format!(
+        "Banned: {}",
+        bans.join(", ")
+    )))
 }
@@ -0,0 +1,196 @@
+use super::prelude::*;
+use crate::database::{next_birthday, Person, User, UserSettings};
+use chrono::prelude::*;
+
+/// How many days `birthday upcoming` looks ahead.
+const UPCOMING_DAYS: i64 = 30;
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("birthday")
+        .alias("birthdays")
+        .arguments(
+            Arguments::new()
+                .subcommand(
+                    "set",
+                    "set your birthday",
+                    Arguments::new().required("date", ArgType::Text),
+                )
+                .subcommand("remove", "remove your birthday", Arguments::new())
+                .subcommand("optin", "get greeted on your birthday", Arguments::new())
+                .subcommand("optout", "stop birthday greetings", Arguments::new())
+                .subcommand(
+                    "upcoming",
+                    "list birthdays in the next 30 days",
+                    Arguments::new(),
+                )
+                .optional("user", ArgType::User),
+        )
+        .command(|context, args, _msg, user| {
+            let conn = &context.conn();
+            let today = Local::today().naive_local();
+
+            match args.subcommand() {
+                Some("set") => set(conn, user, args.text("date").unwrap_or_default(), today),
+                Some("remove") => {
+                    if user.person_id.is_some() {
+                        Person::set_birthday(conn, user, None)?;
+                    }
+                    Ok(MessageResult::Reply(String::from("Removed your birthday")))
+                }
+                Some("optin") => {
+                    UserSettings::get_or_create(conn, user)?.set_birthdays(conn, true)?;
+                    Ok(MessageResult::Reply(String::from(
+                        "You will be greeted on your birthday",
+                    )))
+                }
+                Some("optout") => {
+                    UserSettings::get_or_create(conn, user)?.set_birthdays(conn, false)?;
+                    Ok(MessageResult::Reply(String::from(
+                        "You will not be greeted on your birthday",
+                    )))
+                }
+                Some("upcoming") => upcoming(conn, today),
+                _ => match args.text("user") {
+                    Some(name) => show_other(conn, name, today),
+                    None => show(conn, user, today),
+                },
+            }
+        })
+        .about("Manage your birthday")
+        .description(
+            "
+Set your birthday with `birthday set YYYY-MM-DD`. Birthday greetings are opt-in: use `birthday
+optin` to be greeted on your birthday and `birthday optout` to stop them. Only users that opted in
+are listed by `birthday upcoming`.
+
+Without a sub-command your birthday is shown. The birthday of other users is only shown if they
+opted in.
+",
+        )
+        .example(
+            "
+```
+> ~birthday set 1990-12-24
+< chronophylos, Set your birthday to 24 December. Use `birthday optin` to be greeted
+> ~birthdays upcoming
+< Upcoming birthdays: chronophylos (24 December)
+```",
+        )
+        .done()
+}
+
+fn set(
+    conn: &database::Connection,
+    user: &User,
+    date: &str,
+    today: NaiveDate,
+) -> Result<MessageResult> {
+    let dob = match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+        Ok(dob) => dob,
+        Err(_) => {
+            return Ok(MessageResult::Error(format!(
+                "{} is not a date like YYYY-MM-DD",
+                date
+            )))
+        }
+    };
+
+    if dob > today || dob.year() < 1900 {
+        return Ok(MessageResult::Error(String::from(
+            "That is not a valid birthday",
+        )));
+    }
+
+    Person::set_birthday(conn, user, Some(dob))?;
+
+    let greeted = UserSettings::by_user(conn, user)?.map_or(false, |s| s.birthdays);
+
+    Ok(MessageResult::Reply(format!(
+        "Set your birthday to {}{}",
+        dob.format("%-d %B"),
+        if greeted {
+            ""
+        } else {
+            ". Use `birthday optin` to be greeted"
+        }
+    )))
+}
+
+fn show(conn: &database::Connection, user: &User, today: NaiveDate) -> Result<MessageResult> {
+    let dob = match Person::by_user(conn, user)?.and_then(|p| p.dob) {
+        Some(dob) => dob,
+        None => {
+            return Ok(MessageResult::Reply(String::from(
+                "You have not set your birthday. Use `birthday set YYYY-MM-DD`",
+            )))
+        }
+    };
+
+    let greeted = UserSettings::by_user(conn, user)?.map_or(false, |s| s.birthdays);
+
+    Ok(MessageResult::Reply(format!(
+        "Your birthday is on {} ({}, greetings {})",
+        dob.format("%-d %B"),
+        days_until(dob, today),
+        if greeted { "on" } else { "off" }
+    )))
+}
+
+fn show_other(conn: &database::Connection, name: &str, today: NaiveDate) -> Result<MessageResult> {
+    let not_found = || {
+        Ok(MessageResult::Reply(format!(
+            "I don't know the birthday of {}",
+            name
+        )))
+    };
+
+    let user = match User::by_name(conn, name)? {
+        Some(user) => user,
+        None => return not_found(),
+    };
+
+    if !UserSettings::by_user(conn, &user)?.map_or(false, |s| s.birthdays) {
+        return not_found();
+    }
+
+    match Person::by_user(conn, &user)?.and_then(|p| p.dob) {
+        Some(dob) => Ok(MessageResult::Reply(format!(
+            "The birthday of {} is on {} ({})",
+            user.display_name_or_name(),
+            dob.format("%-d %B"),
+            days_until(dob, today)
+        ))),
+        None => not_found(),
+    }
+}
+
+fn days_until(dob: NaiveDate, today: NaiveDate) -> String {
+    match (next_birthday(dob, today) - today).num_days() {
+        0 => String::from("today"),
+        1 => String::from("tomorrow"),
+        n => format!("in {} days", n),
+    }
+}
+
+fn upcoming(conn: &database::Connection, today: NaiveDate) -> Result<MessageResult> {
+    let birthdays: Vec<String> = Person::upcoming(conn, today, UPCOMING_DAYS)?
+        .into_iter()
+        .map(|(date, user)| {
+            format!(
+                "{} ({})",
+                user.display_name_or_name(),
+                if date == today {
+                    String::from("today")
+                } else {
+                    date.format("%-d %B").to_string()
+                }
+            )
+        })
+        .collect();
+
+    Ok(MessageResult::Message(if birthdays.is_empty() {
+        format!("No birthdays in the next {} days", UPCOMING_DAYS)
+    } else {
+        format!("Upcoming birthdays: {}", birthdays.join(", "))
+    }))
+}
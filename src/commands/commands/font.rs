@@ -1,57 +1,63 @@
 use super::prelude::*;
-use bytes::buf::BufExt as _;
-use bytes::Buf;
-use hyper::Client;
-use hyper::Uri;
-use serde_json::Value;
-use simple_error::SimpleError;
-use std::error::Error;
+use bytes::{buf::BufExt as _, Buf};
+use hyper::{Body, Client, Uri};
+use hyper_rustls::HttpsConnector;
+use serde::Deserialize;
 
-type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+/// Google prefixes the json with `)]}'\n`.
+const JSON_PREFIX_LENGTH: usize = 5;
 
 #[derive(Deserialize, Debug)]
 struct FontStats {
-    pub totalViews: u64,
+    #[serde(rename = "totalViews")]
+    total_views: u64,
 }
 
-pub fn command() -> Arc<Command> {
-    let uri = Uri::from_static("https://fonts.google.com/analytics");
-
-    // todo cache
-    async fn stats(uri: Uri) -> Result<FontStats> {
-        let client = Client::new();
-        let resp = client.get(uri).await?;
-
-        if !resp.status().is_success() {
-            SimpleError::new("Status code is not Ok");
-        }
-
-        let body = hyper::body::aggregate(resp).await?;
+// todo cache
+async fn stats() -> Result<FontStats> {
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+    let resp = client
+        .get(Uri::from_static("https://fonts.google.com/analytics"))
+        .await
+        .context("Could not request font stats")?;
 
-        // yank first 5 bytes
-        body.advance(5);
+    ensure!(
+        resp.status().is_success(),
+        "Status code is not Ok ({})",
+        resp.status()
+    );
 
-        // try to parse as json with serde_json
-        let stats: FontStats = serde_json::from_reader(body.reader())?;
+    let mut body = hyper::body::aggregate(resp)
+        .await
+        .context("Could not read font stats")?;
 
-        Ok(stats)
-    }
+    body.advance(JSON_PREFIX_LENGTH);
 
-    fn font(name: &str) -> MessageResult {
-        MessageResult::Message(String::from("Not implemented"))
-    }
-
-    fn total() -> MessageResult {
-        let stats = async {
-            stats(uri).await;
-        };
-        MessageResult::Message(format!("Total views: {}", stats.totalViews))
-    }
+    serde_json::from_reader(body.reader()).context("Could not parse font stats")
+}
 
+pub fn command() -> Arc<Command> {
     Command::with_name("font")
-        .command(move |_context, args, msg| match args.get(0) {
-            Some(name) => font(name),
-            None => total(),
+        .async_command(|_context, args, _msg, _user| async move {
+            match args.get(0) {
+                Some(_name) => Ok(MessageResult::Message(String::from("Not implemented"))),
+                None => Ok(MessageResult::Message(format!(
+                    "Total views: {}",
+                    stats().await?.total_views
+                ))),
+            }
         })
+        .about("Get statistics of Google Fonts")
+        .description(
+            "
+=== USAGE
+
+```
+font
+```
+
+Show the total views of all fonts on Google Fonts.
+",
+        )
         .done()
 }
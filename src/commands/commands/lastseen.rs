@@ -5,6 +5,7 @@ use chrono::prelude::*;
 pub fn command() -> Arc<Command> {
     Command::with_name("lastseen")
         .aliases(vec!["ls"])
+        .chainable()
         .command(|context, args, _msg, _user| {
             let name = match args.get(0) {
                 None => return Ok(MessageResult::MissingArgument("name")),
@@ -3,17 +3,18 @@ use super::prelude::*;
 pub fn command() -> Arc<Command> {
     Command::with_name("man")
         .aliases(vec!["help", "whatis", "hilbe"])
+        .arguments(
+            Arguments::new()
+                .optional(
+                    "chapter",
+                    ArgType::OneOf(&["action", "command", "1", "2"]),
+                )
+                .required("page", ArgType::Text),
+        )
         .command(move |context, args, _msg, _user| {
-            if args.is_empty() {
-                return Ok(MessageResult::MissingArgument("page"));
-            }
-
-            let (chapter, name) = match args.get(1) {
-                Some(name) => (args.get(0), name),
-                None => (None, args.get(0).unwrap()),
-            };
-
-            let chapter = chapter.cloned().map(|c| c.into());
+            let chapter = args.text("chapter").map(|c| c.to_owned().into());
+            // page is required
+            let name = args.text("page").unwrap_or_default();
 
             match context.whatis(chapter, name.to_owned()) {
                 Some(m) => Ok(MessageResult::Message(m.short())),
@@ -28,7 +29,9 @@ Chapter `command` contains Commands like this `man (2)` command or the `voicemai
 
 This command gives a short overview over a command. The full manual is available on the web.
 
-USAGE: man [CHAPTER] PAGE
+Commands marked as `chainable` can be combined into a pipeline. The output of a command is appended
+to the arguments of the next one: `~color | ~test` or `~tell me in 1h ~ping`. A nested command like
+`~ping` has to be the last word.
 ")
 .example("
 ```
@@ -1,12 +1,14 @@
 use super::prelude::*;
 use evalexpr::*;
-use std::f64::consts;
+use std::{f64::consts, time::Duration};
 
 static PHI: f64 = 1.61803398874989484820;
 
 pub fn command() -> Arc<Command> {
     Command::with_name("math")
         .alias("quickmafs")
+        .chainable()
+        .user_cooldown(Duration::from_secs(5))
         .command(move |_context, args, _msg, _user| {
             // TODO: cache context
             let context = context_map! {
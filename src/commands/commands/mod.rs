@@ -4,31 +4,47 @@ use std::sync::Arc;
 mod prelude;
 
 mod admin;
+mod birthday;
 mod color;
+mod font;
 mod lastseen;
 mod man;
 mod math;
+mod pasta;
+mod pause;
 mod ping;
+mod prefix;
 mod quote;
+mod resume;
+mod settings;
 mod system;
 mod test;
 mod time;
 mod version;
 mod voicemail;
+mod voicemails;
 
 pub fn all() -> Vec<Arc<Command>> {
     vec![
         admin::command(),
+        birthday::command(),
         color::command(),
+        font::command(),
         lastseen::command(),
         man::command(),
         math::command(),
+        pasta::command(),
+        pause::command(),
         ping::command(),
+        prefix::command(),
         quote::command(),
+        resume::command(),
+        settings::command(),
         system::command(),
         test::command(),
         time::command(),
         version::command(),
         voicemail::command(),
+        voicemails::command(),
     ]
 }
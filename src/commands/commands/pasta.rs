@@ -0,0 +1,231 @@
+use super::prelude::*;
+use crate::{
+    database::{Copypasta, User},
+    twitchbot::MAX_TEXT_LENGTH,
+};
+
+/// The maximum length of a name in characters.
+const MAX_NAME_LENGTH: usize = 25;
+
+/// Names that would be shadowed by a sub-command.
+const RESERVED_NAMES: &[&str] = &["add", "edit", "remove", "delete", "list", "random"];
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("pasta")
+        .aliases(vec!["copypasta", "pastas"])
+        .subcommand_permission("add", Permission::Friend)
+        .subcommand_permission("edit", Permission::Friend)
+        .subcommand_permission("remove", Permission::Friend)
+        .arguments(
+            Arguments::new()
+                .subcommand(
+                    "add",
+                    "add a new copypasta",
+                    Arguments::new()
+                        .required("name", ArgType::Text)
+                        .rest("text"),
+                )
+                .subcommand(
+                    "edit",
+                    "change the text of a copypasta you made",
+                    Arguments::new()
+                        .required("name", ArgType::Text)
+                        .rest("text"),
+                )
+                .subcommand(
+                    "remove",
+                    "remove a copypasta you made",
+                    Arguments::new().required("name", ArgType::Text),
+                )
+                .alias("remove", "delete")
+                .subcommand("list", "list the names of all copypastas", Arguments::new())
+                .subcommand("random", "show a random copypasta", Arguments::new())
+                .required("name", ArgType::Text),
+        )
+        .command(|context, args, msg, user| {
+            let conn = &context.conn();
+            let name = args.text("name").unwrap_or_default().to_lowercase();
+            let text = args.text("text").unwrap_or_default();
+
+            match args.subcommand() {
+                Some("add") => add(conn, user, &name, text),
+                Some("edit") => {
+                    let permission = Permission::from_user(conn, &msg, user)?;
+                    edit(conn, user, permission, &name, text)
+                }
+                Some("remove") => {
+                    let permission = Permission::from_user(conn, &msg, user)?;
+                    remove(conn, user, permission, &name)
+                }
+                Some("list") => list(conn),
+                Some("random") => Ok(MessageResult::Message(match Copypasta::random(conn)? {
+                    Some(pasta) => pasta.message,
+                    None => String::from("There are no copypastas yet"),
+                })),
+                _ => Ok(MessageResult::Message(
+                    match Copypasta::by_name(conn, &name)? {
+                        Some(pasta) => pasta.message,
+                        None => format!("No copypasta named {} found", name),
+                    },
+                )),
+            }
+        })
+        .about("Show or manage copypastas")
+        .description(
+            "
+NOTE: You need friend permissions to add copypastas. Only the creator of a copypasta and owners can
+edit or remove it.
+
+Names are case insensitive and unique. A name can be at most 25 characters long and a copypasta at
+most 498 characters, so that it fits into one message.
+",
+        )
+        .example(
+            "
+```
+> ~pasta add hello Hello there!
+< Added copypasta hello
+> ~pasta hello
+< Hello there!
+```",
+        )
+        .done()
+}
+
+fn check_text(text: &str) -> Option<MessageResult> {
+    if text.chars().count() > MAX_TEXT_LENGTH {
+        return Some(MessageResult::Error(format!(
+            "Copypasta is too long, max length is {}",
+            MAX_TEXT_LENGTH
+        )));
+    }
+
+    None
+}
+
+fn add(conn: &database::Connection, user: &User, name: &str, text: &str) -> Result<MessageResult> {
+    if name.chars().count() > MAX_NAME_LENGTH {
+        return Ok(MessageResult::Error(format!(
+            "Name is too long, max length is {}",
+            MAX_NAME_LENGTH
+        )));
+    }
+
+    if RESERVED_NAMES.contains(&name) {
+        return Ok(MessageResult::Error(format!(
+            "{} is not a valid name",
+            name
+        )));
+    }
+
+    if let Some(err) = check_text(text) {
+        return Ok(err);
+    }
+
+    if Copypasta::by_name(conn, name)?.is_some() {
+        return Ok(MessageResult::Error(format!(
+            "A copypasta named {} already exists",
+            name
+        )));
+    }
+
+    let pasta = Copypasta::new(conn, user.id, name, text)?;
+
+    info!("Added copypasta (name: {}, id: {})", pasta.name, pasta.id);
+
+    Ok(MessageResult::Message(format!("Added copypasta {}", name)))
+}
+
+/// Get the copypasta `name` if `user` may change it.
+fn editable(
+    conn: &database::Connection,
+    user: &User,
+    permission: Permission,
+    name: &str,
+) -> Result<std::result::Result<Copypasta, MessageResult>> {
+    let pasta = match Copypasta::by_name(conn, name)? {
+        Some(p) => p,
+        None => {
+            return Ok(Err(MessageResult::Message(format!(
+                "No copypasta named {} found",
+                name
+            ))))
+        }
+    };
+
+    if pasta.creator_id != user.id && permission != Permission::Owner {
+        return Ok(Err(MessageResult::Message(String::from(
+            "You do not have permissions for this copypasta",
+        ))));
+    }
+
+    Ok(Ok(pasta))
+}
+
+fn edit(
+    conn: &database::Connection,
+    user: &User,
+    permission: Permission,
+    name: &str,
+    text: &str,
+) -> Result<MessageResult> {
+    if let Some(err) = check_text(text) {
+        return Ok(err);
+    }
+
+    let pasta = match editable(conn, user, permission, name)? {
+        Ok(p) => p,
+        Err(result) => return Ok(result),
+    };
+
+    pasta.update(conn, text)?;
+
+    Ok(MessageResult::Message(format!(
+        "Updated copypasta {}",
+        name
+    )))
+}
+
+fn remove(
+    conn: &database::Connection,
+    user: &User,
+    permission: Permission,
+    name: &str,
+) -> Result<MessageResult> {
+    let pasta = match editable(conn, user, permission, name)? {
+        Ok(p) => p,
+        Err(result) => return Ok(result),
+    };
+
+    pasta.remove(conn)?;
+
+    info!("Removed copypasta (name: {})", name);
+
+    Ok(MessageResult::Message(format!(
+        "Removed copypasta {}",
+        name
+    )))
+}
+
+fn list(conn: &database::Connection) -> Result<MessageResult> {
+    let names: Vec<String> = Copypasta::all(conn)?.into_iter().map(|p| p.name).collect();
+
+    Ok(MessageResult::Message(if names.is_empty() {
+        String::from("There are no copypastas yet")
+    } else {
+        format!("Copypastas: {}", names.join(", "))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_text() {
+        assert!(check_text("Hello there!").is_none());
+        assert!(check_text(&"a".repeat(MAX_TEXT_LENGTH)).is_none());
+        assert!(check_text(&"a".repeat(499)).is_some());
+        assert!(check_text(&"a".repeat(500)).is_some());
+    }
+}
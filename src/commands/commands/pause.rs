@@ -0,0 +1,46 @@
+use super::prelude::*;
+use crate::database::Channel;
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("pause")
+        .requirement(Requirement::ChannelBroadcasterOrOwner)
+        .command(|context, _args, msg, _user| {
+            let conn = &context.conn();
+            let channel_name = msg.channel().trim_start_matches('#').to_owned();
+            let channel = Channel::by_name(conn, &channel_name)
+                .context("Could not get channel from database")?
+                .context("Channel is not in database")?;
+
+            if channel.paused {
+                return Ok(MessageResult::Message(String::from(
+                    "I am already paused in this channel",
+                )));
+            }
+
+            channel.set_paused(conn, true)?;
+
+            info!("Paused channel (name: {})", channel_name);
+
+            Ok(MessageResult::Message(format!(
+                "Paused. Use {}resume to resume",
+                channel.prefix.unwrap_or_else(|| context.prefix())
+            )))
+        })
+        .about("Pause the bot in this channel")
+        .description(
+            "
+NOTE: Only the broadcaster or an owner of the bot can pause the bot!
+
+=== USAGE
+
+```
+pause
+```
+
+While paused the bot stays in the channel but ignores all commands and actions except `resume`.
+Voicemails scheduled for this channel are held and delivered after the channel is resumed.
+The state is kept across restarts.
+",
+        )
+        .done()
+}
@@ -0,0 +1,96 @@
+use super::prelude::*;
+use crate::database::Channel;
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("prefix")
+        .subcommand_requirement("set", Requirement::ChannelBroadcasterOrOwner)
+        .subcommand_requirement("reset", Requirement::ChannelBroadcasterOrOwner)
+        .command(|context, args, msg, _user| {
+            let conn = &context.conn();
+            let channel_name = msg.channel().trim_start_matches('#').to_owned();
+            let channel = Channel::by_name(conn, &channel_name)
+                .context("Could not get channel from database")?
+                .context("Channel is not in database")?;
+
+            match args.get(0).map(String::as_str) {
+                None => {
+                    return Ok(MessageResult::Message(format!(
+                        "The prefix in this channel is \"{}\"",
+                        channel.prefix.unwrap_or_else(|| context.prefix())
+                    )))
+                }
+                Some("reset") => {
+                    channel.set_prefix(conn, None)?;
+                    context.set_channel_prefix(&channel_name, None);
+
+                    return Ok(MessageResult::Message(format!(
+                        "Reset the prefix to \"{}\"",
+                        context.prefix()
+                    )));
+                }
+                Some("set") if args.len() > 1 => {}
+                Some("set") => return Ok(MessageResult::MissingArgument("prefix")),
+                Some(_) => {
+                    return Ok(MessageResult::Error(String::from(
+                        "Use `prefix set PREFIX` or `prefix reset`",
+                    )))
+                }
+            }
+
+            // quotes allow prefixes with trailing whitespace like `"chb "`
+            let line = args[1..].join(" ");
+            let prefix = line.trim_matches('"');
+
+            if prefix.trim().is_empty() {
+                return Ok(MessageResult::Error("The prefix cannot be empty".into()));
+            }
+
+            if prefix.chars().count() > 16 {
+                return Ok(MessageResult::Error(
+                    "The prefix is too long, max length is 16".into(),
+                ));
+            }
+
+            channel.set_prefix(conn, Some(prefix))?;
+            context.set_channel_prefix(&channel_name, Some(prefix));
+
+            info!(
+                "Changed prefix (channel: {}, prefix: {})",
+                channel_name, prefix
+            );
+
+            Ok(MessageResult::Message(format!(
+                "Changed the prefix to \"{}\"",
+                prefix
+            )))
+        })
+        .about("Show or change the command prefix of this channel")
+        .description(
+            r#"
+NOTE: Only the broadcaster or an owner of the bot can change the prefix!
+
+=== USAGE
+
+```
+prefix
+prefix set PREFIX
+prefix reset
+```
+
+Without arguments the current prefix is shown.
+Prefixes can be longer than one character.
+Use quotes if the prefix should end with a space.
+`reset` restores the global default.
+"#,
+        )
+        .example(
+            r#"
+```
+> ~prefix set "chb "
+< Changed the prefix to "chb "
+> chb ping
+< Pong! ...
+```"#,
+        )
+        .done()
+}
@@ -1,8 +1,11 @@
 pub use crate::{
-    commands::command::Command,
+    commands::{
+        arguments::{ArgType, Arguments},
+        command::Command,
+    },
     context::BotContext,
     database,
-    helpers::{truncate_duration, Permission},
+    helpers::{truncate_duration, Permission, Requirement},
     message::{Message, MessageResult},
 };
 pub use anyhow::{bail, ensure, Context, Result};
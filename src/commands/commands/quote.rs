@@ -1,6 +1,6 @@
 use super::prelude::*;
 
-use crate::database::{Quote, User};
+use crate::database::{Channel, Quote, User};
 use crate::helpers::Permission;
 use regex::Regex;
 
@@ -14,33 +14,93 @@ lazy_static! {
 pub fn command() -> Arc<Command> {
     Command::with_name("quote")
         .alias("quotes")
-        .command(
-            move |context, args, msg, user| match args.get(0).map(String::as_str) {
-                Some("add") => add(context.clone(), msg, user, args[1..].to_vec()),
-                Some("remove") | Some("delete") => {
-                    remove(context.clone(), msg, user, args.get(1).map(String::as_str))
-                }
-                Some("edit") => edit(context.clone(), msg, user, args[1..].to_vec()),
-                Some("show") => show(context.clone(), args.get(1).map(String::as_str)),
-                Some(qid) => show(context.clone(), Some(qid)),
-                None => Ok(MessageResult::Message(String::from("Missing sub-command"))),
-            },
+        .subcommand_permission("add", Permission::Friend)
+        .subcommand_permission("remove", Permission::Friend)
+        .subcommand_permission("edit", Permission::Friend)
+        .arguments(
+            Arguments::new()
+                .subcommand("add", "add a new quote", Arguments::new().rest("quote"))
+                .subcommand(
+                    "remove",
+                    "remove a quote",
+                    Arguments::new().required("id", ArgType::Integer),
+                )
+                .alias("remove", "delete")
+                .subcommand(
+                    "edit",
+                    "edit a quote you made",
+                    Arguments::new()
+                        .required("id", ArgType::Integer)
+                        .rest("quote"),
+                )
+                .subcommand(
+                    "show",
+                    "show a quote",
+                    Arguments::new().required("id", ArgType::Integer),
+                )
+                .subcommand(
+                    "random",
+                    "show a random quote from this channel or from all channels",
+                    Arguments::new().flag("all").optional_rest("author"),
+                )
+                .subcommand(
+                    "search",
+                    "search quotes by text or author",
+                    Arguments::new().rest("text"),
+                )
+                .subcommand(
+                    "by",
+                    "list the quotes of an author",
+                    Arguments::new().rest("author"),
+                )
+                .subcommand("count", "count all quotes", Arguments::new())
+                .required("id", ArgType::Integer),
         )
+        .command(move |context, args, msg, user| {
+            let text = args.text("quote").unwrap_or_default();
+
+            match args.subcommand() {
+                Some("add") => return add(context.clone(), msg, user, text),
+                Some("random") => {
+                    return random(&context.conn(), &msg, args.text("author"), args.flag("all"))
+                }
+                Some("search") => {
+                    let text = args.text("text").unwrap_or_default();
+                    let quotes = Quote::search(&context.conn(), text)?;
+                    return Ok(MessageResult::Message(summarize(quotes)));
+                }
+                Some("by") => {
+                    let author = args.text("author").unwrap_or_default();
+                    let quotes = Quote::by_author(&context.conn(), author)?;
+                    return Ok(MessageResult::Message(summarize(quotes)));
+                }
+                Some("count") => {
+                    let count = Quote::count(&context.conn())?;
+                    return Ok(MessageResult::Message(format!(
+                        "There are {} quotes",
+                        count
+                    )));
+                }
+                _ => {}
+            }
+
+            // the other sub-commands need an id
+            let qid = match args.value::<i32>("id") {
+                Some(qid) => qid,
+                None => return Ok(MessageResult::Error(String::from("Invalid quote id"))),
+            };
+
+            match args.subcommand() {
+                Some("remove") => remove(context.clone(), msg, user, qid),
+                Some("edit") => edit(context.clone(), msg, user, qid, text),
+                _ => show(context.clone(), qid),
+            }
+        })
         .about("Show or manage quotes")
         .description(
             "
-USAGE: quote SUBCOMMAND
-       quote <quote id>
-
-SUBCOMMANDS:
-    add    - add a new quote
-    remove - remove a quote
-    edit   - edit a quote you made
-    show   - show a quote you made
-    random - show a random quote (Not Implemented)
-
 NOTES:
-    You need friend permissions to add quotes.
+    You need friend permissions to add, edit or remove quotes.
 
     When adding a quote you have to follow a specific format:
     ```
@@ -51,28 +111,17 @@ NOTES:
         .done()
 }
 
-fn add(
-    context: Arc<BotContext>,
-    msg: Message,
-    user: &User,
-    args: Vec<String>,
-) -> Result<MessageResult> {
-    let permission = Permission::from_user(msg, &user).unwrap();
-
-    // check if permission is at least friend
-    if permission < Permission::Friend {
-        debug!("Permission not high enough");
-        return Ok(MessageResult::None);
-    }
-
-    let msg = args.join(" ");
-    let (message, author, authored) = match parse_quote(&msg) {
+fn add(context: Arc<BotContext>, msg: Message, user: &User, text: &str) -> Result<MessageResult> {
+    let (message, author, authored) = match parse_quote(text) {
         Ok(t) => t,
         Err(err) => return Ok(MessageResult::Error(err.to_string())),
     };
 
+    let conn = &context.conn();
+    let channel_id = channel_id(conn, &msg)?;
+
     // insert quote
-    let quote = Quote::new(&context.conn(), user.id, author, authored, message)?;
+    let quote = Quote::new(conn, user.id, channel_id, author, authored, message)?;
 
     info!("Added quote {} (id: {})", quote, quote.id);
     Ok(MessageResult::Message(format!(
@@ -81,32 +130,8 @@ fn add(
     )))
 }
 
-fn remove(
-    context: Arc<BotContext>,
-    msg: Message,
-    user: &User,
-    qid: Option<&str>,
-) -> Result<MessageResult> {
-    // unwrap option
-    let qid: &str = match qid {
-        Some(qid) => qid,
-        None => {
-            return Ok(MessageResult::Error(String::from(
-                "Missing argument: quote id",
-            )))
-        }
-    };
-
-    let permission = Permission::from_user(msg, &user).unwrap();
-
-    // check if permission is at least friend
-    if permission < Permission::Friend {
-        debug!("Permission not high enough");
-        return Ok(MessageResult::None);
-    }
-
-    // parse str to i32
-    let qid: i32 = qid.parse()?;
+fn remove(context: Arc<BotContext>, msg: Message, user: &User, qid: i32) -> Result<MessageResult> {
+    let permission = Permission::from_user(&context.conn(), &msg, &user)?;
 
     let conn = &context.conn();
 
@@ -142,24 +167,10 @@ fn edit(
     context: Arc<BotContext>,
     msg: Message,
     user: &User,
-    args: Vec<String>,
+    qid: i32,
+    text: &str,
 ) -> Result<MessageResult> {
-    // unwrap option
-    let qid: &str = match args.get(0) {
-        Some(q) => q,
-        None => return Ok(MessageResult::Message(String::from("Missing quote id"))),
-    };
-
-    let permission = Permission::from_user(msg, &user).unwrap();
-
-    // check if permission is at least friend
-    if permission < Permission::Friend {
-        debug!("Permission not high enough");
-        return Ok(MessageResult::None);
-    }
-
-    // parse str to i32
-    let qid: i32 = qid.parse()?;
+    let permission = Permission::from_user(&context.conn(), &msg, &user)?;
 
     let conn = &context.conn();
 
@@ -183,8 +194,7 @@ fn edit(
         )));
     }
 
-    let msg = args.join(" ");
-    let (message, author, authored) = match parse_quote(&msg) {
+    let (message, author, authored) = match parse_quote(text) {
         Ok(t) => t,
         Err(err) => return Ok(MessageResult::Error(err.to_string())),
     };
@@ -197,20 +207,7 @@ fn edit(
     )))
 }
 
-fn show(context: Arc<BotContext>, qid: Option<&str>) -> Result<MessageResult> {
-    // unwrap option
-    let qid: &str = match qid {
-        Some(qid) => qid,
-        None => {
-            return Ok(MessageResult::Error(String::from(
-                "Missing argument: quote id",
-            )))
-        }
-    };
-
-    // parse str to i32
-    let qid: i32 = qid.parse()?;
-
+fn show(context: Arc<BotContext>, qid: i32) -> Result<MessageResult> {
     // query quote
     let quote = Quote::by_id(&context.conn(), qid)?;
 
@@ -220,6 +217,72 @@ fn show(context: Arc<BotContext>, qid: Option<&str>) -> Result<MessageResult> {
     }))
 }
 
+fn channel_id(conn: &database::Connection, msg: &Message) -> Result<Option<i32>> {
+    let channel = Channel::by_name(conn, msg.channel().trim_start_matches('#'))?;
+    Ok(channel.map(|c| c.id))
+}
+
+fn random(
+    conn: &database::Connection,
+    msg: &Message,
+    author: Option<&str>,
+    all: bool,
+) -> Result<MessageResult> {
+    // quotes from the current channel unless all channels are requested
+    let channel_id = if all { None } else { channel_id(conn, msg)? };
+
+    Ok(MessageResult::Message(
+        match Quote::random(conn, author, channel_id)? {
+            Some(q) => format!("#{} {}", q.id, q),
+            None => match (author, channel_id) {
+                (Some(author), None) => format!("No quotes by {} found", author),
+                (Some(author), Some(_)) => format!(
+                    "No quotes by {} found in this channel. Use --all to search all channels",
+                    author
+                ),
+                (None, None) => String::from("There are no quotes yet"),
+                (None, Some(_)) => String::from(
+                    "There are no quotes in this channel yet. Use --all to search all channels",
+                ),
+            },
+        },
+    ))
+}
+
+/// The number of quotes listed in a summary.
+const SUMMARY_LENGTH: usize = 10;
+
+/// The number of characters of a message shown in a summary.
+const SUMMARY_MESSAGE_LENGTH: usize = 30;
+
+/// Show a single quote completely and summarize multiple quotes with their ids.
+fn summarize(quotes: Vec<Quote>) -> String {
+    match quotes.len() {
+        0 => String::from("No quotes found"),
+        1 => format!("#{} {}", quotes[0].id, quotes[0]),
+        n => {
+            let listed: Vec<String> = quotes
+                .iter()
+                .take(SUMMARY_LENGTH)
+                .map(|q| {
+                    let mut message: String =
+                        q.message.chars().take(SUMMARY_MESSAGE_LENGTH).collect();
+                    if q.message.chars().count() > SUMMARY_MESSAGE_LENGTH {
+                        message.push('…');
+                    }
+                    format!("#{} \"{}\"", q.id, message)
+                })
+                .collect();
+
+            let mut summary = format!("Found {} quotes: {}", n, listed.join(", "));
+            if n > SUMMARY_LENGTH {
+                summary.push_str(&format!(" and {} more", n - SUMMARY_LENGTH));
+            }
+            summary
+        }
+    }
+}
+
 fn parse_quote(msg: &str) -> Result<(&str, &str, &str)> {
     // parse quote
     let caps = RE.captures(&msg).context("Regex does not match")?;
@@ -0,0 +1,49 @@
+use super::prelude::*;
+use crate::database::{Channel, Voicemail};
+use chrono::prelude::*;
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("resume")
+        .requirement(Requirement::ChannelBroadcasterOrOwner)
+        .command(|context, _args, msg, _user| {
+            let conn = &context.conn();
+            let channel_name = msg.channel().trim_start_matches('#').to_owned();
+            let channel = Channel::by_name(conn, &channel_name)
+                .context("Could not get channel from database")?
+                .context("Channel is not in database")?;
+
+            if !channel.paused {
+                return Ok(MessageResult::Message(String::from(
+                    "I am not paused in this channel",
+                )));
+            }
+
+            channel.set_paused(conn, false)?;
+
+            info!("Resumed channel (name: {})", channel_name);
+
+            // deliver the voicemails that were held while paused
+            let held = Voicemail::held(conn, channel.id, Utc::now().naive_utc())
+                .context("Could not get held voicemails")?;
+            for voicemail in &held {
+                context.scheduler().schedule_now(voicemail);
+            }
+
+            Ok(MessageResult::Message(String::from("Resumed")))
+        })
+        .about("Resume the bot in this channel")
+        .description(
+            "
+NOTE: Only the broadcaster or an owner of the bot can resume the bot!
+
+=== USAGE
+
+```
+resume
+```
+
+Undo `pause`. Voicemails that were held while paused are delivered.
+",
+        )
+        .done()
+}
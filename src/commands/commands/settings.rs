@@ -0,0 +1,137 @@
+use super::prelude::*;
+use crate::{
+    database::{UserSettings, VOICEMAIL_DELIVERIES},
+    timezone::Zone,
+};
+
+const SWITCH: &[&str] = &["on", "off"];
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("settings")
+        .alias("setting")
+        .arguments(
+            Arguments::new()
+                .subcommand("show", "show your settings", Arguments::new())
+                .subcommand(
+                    "timezone",
+                    "set your timezone, `reset` removes it",
+                    Arguments::new().required("zone", ArgType::Text),
+                )
+                .alias("timezone", "tz")
+                .subcommand(
+                    "birthdays",
+                    "get greeted on your birthday",
+                    Arguments::new().required("value", ArgType::OneOf(SWITCH)),
+                )
+                .subcommand(
+                    "voicemails",
+                    "receive voicemails in chat or as whisper",
+                    Arguments::new().required("value", ArgType::OneOf(VOICEMAIL_DELIVERIES)),
+                )
+                .subcommand(
+                    "mention",
+                    "get mentioned in replies",
+                    Arguments::new().required("value", ArgType::OneOf(SWITCH)),
+                )
+                .subcommand(
+                    "language",
+                    "set your language",
+                    Arguments::new().required("language", ArgType::Text),
+                )
+                .default_subcommand("show"),
+        )
+        .command(|context, args, _msg, user| {
+            let conn = &context.conn();
+            let value = args.text("value").unwrap_or_default();
+
+            let reply = match args.subcommand() {
+                Some("timezone") => {
+                    let zone = args.text("zone").unwrap_or_default();
+
+                    if zone.eq_ignore_ascii_case("reset") {
+                        UserSettings::get_or_create(conn, user)?.set_timezone(conn, None)?;
+                        String::from("Removed your timezone")
+                    } else {
+                        let zone = match Zone::parse(zone) {
+                            Some(z) => z,
+                            None => {
+                                return Ok(MessageResult::Error(format!(
+                                    "Unknown timezone {}. Use a name like Europe/Berlin",
+                                    zone
+                                )))
+                            }
+                        };
+
+                        UserSettings::get_or_create(conn, user)?
+                            .set_timezone(conn, Some(zone.name()))?;
+                        format!("Set your timezone to {}", zone.name())
+                    }
+                }
+                Some("birthdays") => {
+                    UserSettings::get_or_create(conn, user)?.set_birthdays(conn, value == "on")?;
+                    format!("Turned birthday greetings {}", value)
+                }
+                Some("voicemails") => {
+                    UserSettings::get_or_create(conn, user)?.set_voicemail_delivery(conn, value)?;
+                    match value {
+                        "whisper" => String::from("Voicemails are whispered to you"),
+                        _ => String::from("Voicemails are sent in chat"),
+                    }
+                }
+                Some("mention") => {
+                    UserSettings::get_or_create(conn, user)?.set_mention(conn, value == "on")?;
+                    format!("Turned mentions {}", value)
+                }
+                Some("language") => {
+                    let language = args.text("language").unwrap_or_default().to_lowercase();
+
+                    if language.len() != 2 || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+                        return Ok(MessageResult::Error(format!(
+                            "{} is not a language code like en",
+                            language
+                        )));
+                    }
+
+                    UserSettings::get_or_create(conn, user)?.set_language(conn, &language)?;
+                    format!("Set your language to {}", language)
+                }
+                _ => {
+                    let settings = UserSettings::of(conn, user)?;
+
+                    format!(
+                        "timezone: {}, birthdays: {}, voicemails: {}, mention: {}, language: {}",
+                        settings.timezone.as_deref().unwrap_or("not set"),
+                        if settings.birthdays { "on" } else { "off" },
+                        settings.voicemail_delivery,
+                        if settings.mention { "on" } else { "off" },
+                        settings.language
+                    )
+                }
+            };
+
+            Ok(MessageResult::Reply(reply))
+        })
+        .about("Show or change your settings")
+        .description(
+            "
+Without a sub-command your settings are shown.
+
+* `timezone` is used by `time` and for voicemails scheduled at a time. Use a name from the tz
+  database like `Europe/Berlin`, abbreviations like `CET` do not know about daylight saving time.
+* `birthdays` turns birthday greetings on or off. See `birthday`.
+* `voicemails` sets whether voicemails are sent in chat or whispered to you.
+* `mention` sets whether replies start with your name.
+* `language` is a language code like `en`. Messages are only available in English for now.
+",
+        )
+        .example(
+            "
+```
+> ~settings tz Europe/Berlin
+< chronophylos, Set your timezone to Europe/Berlin
+> ~settings
+< chronophylos, timezone: Europe/Berlin, birthdays: off, voicemails: chat, mention: on, language: en
+```",
+        )
+        .done()
+}
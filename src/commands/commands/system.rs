@@ -14,13 +14,14 @@ pub fn command() -> Arc<Command> {
             let uptime = sys.uptime().context("Could not get system uptime")?;
 
             Ok(MessageResult::Message(format!(
-                "Memory usage: {}/{}/{} Load: {} Uptime: {} System Uptime: {}",
+                "Memory usage: {}/{}/{} Load: {} Uptime: {} System Uptime: {} Reconnects: {}",
                 mem_proc.to_string_as(true),
                 mem_used.to_string_as(true),
                 mem_total.to_string_as(true),
                 load_avg.five,
                 humantime::format_duration(truncate_duration(context.elapsed())),
                 humantime::format_duration(truncate_duration(uptime)),
+                context.twitchbot().reconnects(),
             )))
         })
         .about("Get information about the Bot and the Server")
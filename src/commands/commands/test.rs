@@ -3,6 +3,7 @@ use super::prelude::*;
 pub fn command() -> Arc<Command> {
     Command::with_name("test")
         .aliases(vec!["tset", "tets"])
+        .chainable()
         .command(|_context, args, _msg, _user| {
             Ok(MessageResult::Message(if args.is_empty() {
                 "Test what?".into()
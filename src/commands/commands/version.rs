@@ -2,6 +2,7 @@ use super::prelude::*;
 
 pub fn command() -> Arc<Command> {
     Command::with_name("version")
+        .chainable()
         .command(|context, _args, _msg, _user| {
             Ok(MessageResult::Message(format!(
                 "Currently running CHB4 Version {} ({})",
@@ -1,5 +1,11 @@
 use super::prelude::*;
-use crate::voicemail::Voicemail;
+use crate::{
+    commands::arguments::Matches,
+    database::{User, UserSettings},
+    helpers::blocking,
+    timezone::Zone,
+    voicemail::{Schedule, Voicemail},
+};
 use chrono::prelude::*;
 use humantime::format_duration;
 
@@ -10,67 +16,18 @@ lazy_static! {
 pub fn command() -> Arc<Command> {
     Command::with_name("voicemail")
         .alias("tell")
-        .command(move |context, args, msg, _user| {
-            let user_id = msg.twitch_id().unwrap();
-            let line = args.join(" ");
-            let mut voicemail: Voicemail = match line.parse() {
-                Ok(v) => v,
-                Err(err) => {
-                    return Ok(MessageResult::Error(format!(
-                        "Could not parse voicemail: {}",
-                        err
-                    )))
-                }
-            };
-
-            let conn = &context.conn();
-
-            let channel_name = msg.channel().to_owned();
-            let channel = database::Channel::by_name(conn, channel_name.trim_start_matches('#'))
-                .context("Could not get channel from database")?
-                .context("Channel is not in database")?;
-
-            let bot_name = context.bot_name();
-            voicemail.recipients.retain(|x| x != &bot_name);
-
-            let now = Utc::now().naive_utc();
-            let voicemails =
-                database::Voicemail::new(conn, &voicemail, user_id as i64, channel.id, now)
-                    .context("Could not insert voicemail(s) to database")?;
-            if voicemail.schedule.is_none() {
-                Ok(MessageResult::Message(format!(
-                    "I'll send that message to {} when they next type in chat.",
-                    voicemail.recipients.join(", ")
-                )))
-            } else {
-                // actually schedule voicemail
-
-                for voicemail in voicemails {
-                    context.scheduler().schedule(voicemail).unwrap();
-                }
-
-                Ok(MessageResult::Message(format!(
-                    "I'll send that message to {} in {}",
-                    voicemail.recipients.join(", "),
-                    format_duration(
-                        voicemail
-                            .schedule
-                            .unwrap()
-                            .signed_duration_since(now)
-                            .to_std()
-                            .unwrap_or_default()
-                    ),
-                )))
-            }
+        .chainable()
+        .arguments(Arguments::new().rest("voicemail"))
+        .async_command(|context, args, msg, user| async move {
+            blocking(move || send(context, args, msg, user)).await?
         })
         .about("Send messages to other users or yourself")
         .description(
             "
-=== USAGE
+`VOICEMAIL` is `RECIPIENTS [SCHEDULE] MESSAGE`.
+Voicemails without a schedule are delivered when the recipient next types in chat.
 
-```
-tell RECIPIENTS [SCHEDULE] MESSAGE
-```
+Use `voicemails` to list, cancel or edit the voicemails you sent.
 
 ==== RECIPIENTS
 
@@ -96,14 +53,123 @@ To schedule a voicemail you need a marker and a value.
 
 | absolute
 | `on`, `at`
-| A https://tools.ietf.org/html/rfc3339[RFC3339] Date-Time formatted string.
-RFC2822 and keywords like `noon` are wip.
+| A https://tools.ietf.org/html/rfc3339[RFC3339] or https://tools.ietf.org/html/rfc2822[RFC2822]
+date, a date like `24.12.` or `24.12.2020`, a weekday like `friday` or a time like `18:00`.
+Dates and weekdays can be followed by a time. After `at` the time can also be `noon`, `midnight`
+or an hour like `9`.
+
+| tomorrow
+| `tomorrow`
+| A time, like `at 9` or `18:00`
 |===
 
+Times without a date are the next time the clock shows that time.
+
+Dates and times are in your timezone (see `settings timezone`) or UTC if you have not set one.
+Add a zone to use another one, like `at 18:00 CET` or `on friday 9:00 America/New_York`.
+Abbreviations have to be uppercase and do not know about daylight saving time.
+
 .Example:
     in 20 minutes 2 hours
     at 2020-02-20 20:20
+    at noon
+    tomorrow at 9
+    on friday 18:00
+    on 24.12.
+    at 18:00 Europe/Berlin
 ",
         )
         .done()
 }
+
+fn send(
+    context: Arc<BotContext>,
+    args: Matches,
+    msg: Message,
+    user: User,
+) -> Result<MessageResult> {
+    let line = args.text("voicemail").unwrap_or_default();
+    let mut voicemail: Voicemail = match line.parse() {
+        Ok(v) => v,
+        Err(err) => {
+            return Ok(MessageResult::Error(format!(
+                "Could not parse voicemail: {}",
+                err
+            )))
+        }
+    };
+
+    let conn = &context.conn();
+
+    let channel_name = msg.channel().to_owned();
+    let channel = database::Channel::by_name(conn, channel_name.trim_start_matches('#'))
+        .context("Could not get channel from database")?
+        .context("Channel is not in database")?;
+
+    let bot_name = context.bot_name();
+    voicemail.recipients.retain(|x| x != &bot_name);
+
+    let now = Utc::now().naive_utc();
+    // a zone in the schedule wins over the timezone of the sender
+    let zone = match voicemail.schedule.as_ref().and_then(Schedule::zone) {
+        Some(zone) => zone.clone(),
+        None => UserSettings::of(conn, &user)
+            .context("Could not get user settings")?
+            .zone()
+            .unwrap_or_else(Zone::utc),
+    };
+
+    let scheduled = match &voicemail.schedule {
+        Some(schedule) => match schedule.resolve(now, &zone) {
+            Some(scheduled) if scheduled > now => Some(scheduled),
+            Some(_) => {
+                return Ok(MessageResult::Error(String::from(
+                    "That time has passed already",
+                )))
+            }
+            None => {
+                return Ok(MessageResult::Error(format!(
+                    "That time does not exist in {}",
+                    zone
+                )))
+            }
+        },
+        None => None,
+    };
+
+    let voicemails = database::Voicemail::new(conn, &voicemail, &user, channel.id, now, scheduled)
+        .context("Could not insert voicemail(s) to database")?;
+
+    match scheduled {
+        None => Ok(MessageResult::Message(format!(
+            "I'll send that message to {} when they next type in chat.",
+            voicemail.recipients.join(", ")
+        ))),
+        Some(scheduled) => {
+            // actually schedule voicemail
+
+            for voicemail in voicemails {
+                context
+                    .scheduler()
+                    .schedule(voicemail)
+                    .context("Could not schedule voicemail")?;
+            }
+
+            let utc = DateTime::<Utc>::from_utc(scheduled, Utc);
+
+            Ok(MessageResult::Message(format!(
+                "I'll send that message to {} in {} ({}, {} {})",
+                voicemail.recipients.join(", "),
+                format_duration(
+                    (scheduled - now)
+                        .to_std()
+                        .map(truncate_duration)
+                        .unwrap_or_default()
+                ),
+                utc.format("%Y-%m-%d %H:%M UTC"),
+                zone.at(&utc).format("%H:%M"),
+                zone
+            )))
+        }
+    }
+}
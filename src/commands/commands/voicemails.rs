@@ -0,0 +1,169 @@
+use super::prelude::*;
+use crate::{commands::arguments::Matches, database::User, helpers::blocking};
+use chrono::prelude::*;
+
+pub fn command() -> Arc<Command> {
+    Command::with_name("voicemails")
+        .arguments(
+            Arguments::new()
+                .subcommand(
+                    "pending",
+                    "list your voicemails that were not delivered yet",
+                    Arguments::new(),
+                )
+                .subcommand(
+                    "cancel",
+                    "cancel a voicemail you sent",
+                    Arguments::new().required("id", ArgType::Integer),
+                )
+                .subcommand(
+                    "edit",
+                    "change the message of a voicemail you sent",
+                    Arguments::new()
+                        .required("id", ArgType::Integer)
+                        .rest("message"),
+                )
+                .subcommand(
+                    "inbox",
+                    "count the voicemails waiting for you",
+                    Arguments::new().required("what", ArgType::OneOf(&["count"])),
+                )
+                .default_subcommand("pending"),
+        )
+        .async_command(|context, args, _msg, user| async move {
+            match args.subcommand() {
+                Some("cancel") => cancel(context, args, user).await,
+                Some("edit") => blocking(move || edit(context, args, user)).await?,
+                Some("inbox") => blocking(move || inbox(context, user)).await?,
+                _ => blocking(move || pending(context, user)).await?,
+            }
+        })
+        .about("Manage the voicemails you sent and received")
+        .description(
+            "
+`pending` lists the ids of your voicemails that were not delivered yet.
+Use them to `cancel` a voicemail or `edit` its message.
+
+`inbox count` counts the voicemails that wait for you.
+
+Voicemails are sent with `voicemail`.
+",
+        )
+        .example(
+            "
+```
+> ~voicemails
+< Pending voicemails: #42 to bob at 2020-06-12 18:00 UTC
+> ~voicemails edit 42 happy birthday!
+< Changed the message of voicemail #42
+> ~voicemails cancel 42
+< Cancelled voicemail #42
+```",
+        )
+        .done()
+}
+
+/// Get the voicemail `id` if it was sent by `user` and was not delivered yet.
+fn own_pending(
+    conn: &database::Connection,
+    id: i32,
+    user: &User,
+) -> Result<Option<database::Voicemail>> {
+    Ok(database::Voicemail::by_id(conn, id)?.filter(|v| v.creator_id == user.id && v.active))
+}
+
+fn not_pending(id: i32) -> MessageResult {
+    MessageResult::Error(format!("You have no pending voicemail #{}", id))
+}
+
+fn pending(context: Arc<BotContext>, user: User) -> Result<MessageResult> {
+    let conn = &context.conn();
+    let voicemails = database::Voicemail::pending(conn, user.id)?;
+
+    if voicemails.is_empty() {
+        return Ok(MessageResult::Reply(String::from(
+            "You have no pending voicemails",
+        )));
+    }
+
+    let voicemails = voicemails
+        .iter()
+        .map(|v| {
+            let receiver = User::by_id(conn, v.receiver_id)?
+                .map_or_else(|| String::from("unknown"), |u| u.display_name_or_name());
+
+            Ok(format!(
+                "#{} to {}{}",
+                v.id,
+                receiver,
+                match v.scheduled {
+                    Some(scheduled) => format!(" at {}", scheduled.format("%Y-%m-%d %H:%M UTC")),
+                    None => String::new(),
+                }
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(MessageResult::Reply(format!(
+        "Pending voicemails: {}",
+        voicemails.join(", ")
+    )))
+}
+
+async fn cancel(context: Arc<BotContext>, args: Matches, user: User) -> Result<MessageResult> {
+    let id = args.value("id").unwrap_or_default();
+
+    let cancelled = {
+        let context = context.clone();
+        blocking(move || -> Result<bool> {
+            let conn = &context.conn();
+            match own_pending(conn, id, &user)? {
+                Some(voicemail) => {
+                    voicemail.set_active(conn, false)?;
+                    Ok(true)
+                }
+                None => Ok(false),
+            }
+        })
+        .await??
+    };
+
+    if !cancelled {
+        return Ok(not_pending(id));
+    }
+
+    // voicemails without a schedule are not queued
+    context.scheduler().cancel(id).await;
+
+    Ok(MessageResult::Reply(format!("Cancelled voicemail #{}", id)))
+}
+
+fn edit(context: Arc<BotContext>, args: Matches, user: User) -> Result<MessageResult> {
+    let id = args.value("id").unwrap_or_default();
+    let conn = &context.conn();
+
+    let voicemail = match own_pending(conn, id, &user)? {
+        Some(v) => v,
+        None => return Ok(not_pending(id)),
+    };
+
+    voicemail.set_message(conn, args.text("message").unwrap_or_default())?;
+
+    Ok(MessageResult::Reply(format!(
+        "Changed the message of voicemail #{}",
+        id
+    )))
+}
+
+fn inbox(context: Arc<BotContext>, user: User) -> Result<MessageResult> {
+    let count = database::Voicemail::count_inbox(&context.conn(), user.id, Utc::now().naive_utc())?;
+
+    Ok(MessageResult::Reply(match count {
+        0 => String::from("No voicemails are waiting for you"),
+        1 => String::from("One voicemail is waiting for you. Type in chat to get it"),
+        n => format!(
+            "{} voicemails are waiting for you. Type in chat to get them",
+            n
+        ),
+    }))
+}
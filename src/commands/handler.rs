@@ -2,23 +2,36 @@
 use super::command::Command;
 use crate::{
     context::BotContext,
-    database::User,
+    cooldown::CooldownTracker,
+    database::{Channel, User, UserSettings},
     handler::{Handler, SimpleHandler, Twitch},
+    helpers::{blocking, Permission},
     message::{Message, MessageConsumer, MessageResult},
 };
 use anyhow::{Context, Result};
 use async_trait::async_trait;
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Instant};
 use thiserror::Error;
-use twitchchat::messages::Privmsg;
 
 #[derive(Error, Debug)]
 pub enum CommandHandlerError {
     #[error("Could not execute command (name: {0})")]
     ExecuteCommand(String),
 
-    #[error("Could not send privmsg")]
-    SendPrivmsg,
+    #[error("Could not get channel")]
+    GetChannel,
+
+    #[error("Channel not found")]
+    ChannelNotFound,
+
+    #[error("Could not get command filter")]
+    GetFilter,
+
+    #[error("Could not get permission of user")]
+    GetPermission,
+
+    #[error("Could not get settings of user")]
+    GetSettings,
 }
 
 pub struct CommandHandler {
@@ -26,10 +39,8 @@ pub struct CommandHandler {
     // translate aliases to command names
     aliases: HashMap<String, String>,
 
-    /// The prefix to use when checking for commands in a message.
-    prefix: char,
+    cooldowns: CooldownTracker,
 
-    #[allow(dead_code)]
     context: Arc<BotContext>,
 }
 
@@ -50,7 +61,7 @@ impl CommandHandler {
             context,
             commands: command_map,
             aliases,
-            prefix: '~',
+            cooldowns: CooldownTracker::new(),
         }
     }
 }
@@ -71,74 +82,414 @@ impl Handler<Command> for CommandHandler {
 
 #[async_trait]
 impl Twitch for CommandHandler {
-    async fn handle(&self, msg: Arc<Privmsg<'_>>, user: &User) -> Result<()> {
-        let message = msg.data.trim().replace("\u{e0000}", ""); // remove chatterino chars
-        let words: Vec<String> = message.split_whitespace().map(|s| s.to_string()).collect();
-        let mut command_name = words[0].clone();
-        let prefix = command_name.remove(0);
-
-        if prefix != self.prefix {
-            trace!("Prefix not found");
-            return Ok(());
-        }
+    async fn handle(self: Arc<Self>, msg: Message<'static>, user: &User) -> Result<()> {
+        let (pipeline, mention) = {
+            let this = self.clone();
+            let (msg, user) = (msg.clone(), user.clone());
 
-        let args = &words[1..];
-        trace!("Command: {} Args: {:?}", command_name, args);
+            // database queries are blocking
+            blocking(move || -> Result<_> {
+                let prepared = this.prepare(&msg, &user)?;
 
-        let cmd = match self.get(command_name) {
-            Some(c) => c,
-            None => {
-                trace!("No matching command found");
+                // most chat lines are not commands, nothing is sent for them
+                let mention = match &prepared {
+                    Prepared::Respond(MessageResult::None) => false,
+                    _ => {
+                        UserSettings::of(&this.context.conn(), &user)
+                            .context(CommandHandlerError::GetSettings)?
+                            .mention
+                    }
+                };
+
+                Ok((prepared, mention))
+            })
+            .await??
+        };
+
+        let pipeline = match pipeline {
+            Prepared::Run(pipeline) => pipeline,
+            Prepared::Respond(result) => {
+                self.context
+                    .twitchbot()
+                    .respond(&msg, user, result, mention);
                 return Ok(());
             }
         };
 
-        debug!("Found matching command {}", Command::name(&cmd));
+        trace!("Executing command (stages: {})", pipeline.len());
+
+        let mut result = MessageResult::None;
+        let mut input: Option<String> = None;
+
+        for (cmd, mut args) in pipeline {
+            // the output of the previous command is appended to the arguments
+            if let Some(text) = input.take() {
+                args.extend(text.split_whitespace().map(String::from));
+            }
+
+            let name = cmd.name().to_owned();
+            result = cmd
+                .consume(self.context.clone(), args, msg.clone(), user.clone())
+                .await
+                .context(CommandHandlerError::ExecuteCommand(name))?;
 
-        if cmd.whitelisted() {
-            debug!(
-                "Command is not whitelisted in this channel (name: {}, channel: {})",
-                cmd.name(),
-                msg.channel
-            );
-            return Ok(());
+            match &result {
+                MessageResult::Reply(m) | MessageResult::Message(m) => input = Some(m.clone()),
+                _ => break,
+            }
         }
 
-        // or the command is enabled in this channel
+        self.context
+            .twitchbot()
+            .respond(&msg, user, result, mention);
 
-        let mut writer = self.context.twitchbot().writer();
+        Ok(())
+    }
+}
 
-        trace!("Executing command");
+/// The outcome of preparing a message.
+enum Prepared {
+    /// Respond without executing any command.
+    Respond(MessageResult),
+    /// Execute the commands in order.
+    Run(Vec<(Arc<Command>, Vec<String>)>),
+}
 
-        match cmd
-            .consume(
-                self.context.clone(),
-                args.to_vec(),
-                Message::TwitchPrivmsg(msg.clone()),
-                user,
-            )
-            .context(CommandHandlerError::ExecuteCommand(cmd.name().to_owned()))?
+impl CommandHandler {
+    /// Parse `msg` into a pipeline and check filters, permissions and cooldowns.
+    fn prepare(&self, msg: &Message<'static>, user: &User) -> Result<Prepared> {
+        let message = msg.text().trim().replace("\u{e0000}", ""); // remove chatterino chars
+
+        let channel_name = msg.channel().trim_start_matches('#');
+
+        // The prefix of the channel takes precedence over the global default. It is cached, so
+        // most chat lines are skipped without a database query
+        let prefix = self
+            .context
+            .channel_prefix(channel_name)
+            .context(CommandHandlerError::GetChannel)?;
+
+        let line = match message.strip_prefix(prefix.as_str()) {
+            Some(l) => l,
+            None => {
+                trace!("Prefix not found");
+                return Ok(Prepared::Respond(MessageResult::None));
+            }
+        };
+
+        let channel = Channel::by_name(&self.context.conn(), channel_name)
+            .context(CommandHandlerError::GetChannel)?
+            .context(CommandHandlerError::ChannelNotFound)?;
+
+        let get = |name: &str| self.get(name.to_owned());
+        let stages: Vec<Stage> = split_pipeline(line, &prefix)
+            .into_iter()
+            .flat_map(|segment| parse_segment(segment, &prefix, &get))
+            .collect();
+
+        // only the first command decides if this message is meant for us
+        match stages.first() {
+            Some(Stage {
+                command: Some(cmd), ..
+            }) => debug!("Found matching command {}", Command::name(cmd)),
+            _ => {
+                trace!("No matching command found");
+                return Ok(Prepared::Respond(MessageResult::None));
+            }
+        }
+
+        // a paused channel only listens to `resume`
+        if channel.paused
+            && stages
+                .iter()
+                .any(|s| s.command.as_ref().map_or(true, |c| c.name() != "resume"))
         {
-            MessageResult::None => Ok(()),
-            MessageResult::Reply(m) => writer
-                .privmsg(
-                    &msg.channel,
-                    format!("{}, {}", user.display_name_or_name(), m).as_str(),
-                )
-                .await
-                .context(CommandHandlerError::SendPrivmsg),
-            MessageResult::Message(m) => writer
-                .privmsg(&msg.channel, &m)
-                .await
-                .context(CommandHandlerError::SendPrivmsg),
-            MessageResult::Error(m) => writer
-                .privmsg(&msg.channel, format!("Error: {}", m))
-                .await
-                .context(CommandHandlerError::SendPrivmsg),
-            MessageResult::MissingArgument(a) => writer
-                .privmsg(&msg.channel, format!("Missing argument `{}`", a))
-                .await
-                .context(CommandHandlerError::SendPrivmsg),
+            debug!("Channel is paused (channel: {})", msg.channel());
+            return Ok(Prepared::Respond(MessageResult::None));
         }
+
+        let chained = stages.len() > 1;
+        let mut pipeline = Vec::new();
+
+        let global = Permission::global(user);
+        let in_channel = Permission::in_channel(&self.context.conn(), msg, user)
+            .context(CommandHandlerError::GetPermission)?;
+
+        // moderators and broadcasters are exempt from cooldowns
+        let exempt = global.max(in_channel) >= Permission::Moderator;
+
+        for stage in stages {
+            let cmd = match stage.command {
+                Some(c) => c,
+                None => {
+                    return Ok(Prepared::Respond(MessageResult::Error(format!(
+                        "Unknown command `{}` in pipeline",
+                        stage.name
+                    ))))
+                }
+            };
+
+            // whitelisted commands are disabled unless enabled explicitly
+            let enabled = channel
+                .command_filter(&self.context.conn(), cmd.name())
+                .context(CommandHandlerError::GetFilter)?
+                .unwrap_or(!cmd.whitelisted());
+
+            if !enabled {
+                debug!(
+                    "Command is not enabled in this channel (name: {}, channel: {})",
+                    cmd.name(),
+                    msg.channel()
+                );
+                return Ok(Prepared::Respond(MessageResult::None));
+            }
+
+            let needed = cmd.requirement(&stage.args);
+            if !needed.is_met(global, in_channel) {
+                debug!(
+                    "Permission not high enough (name: {}, global: {}, channel: {}, needed: {})",
+                    cmd.name(),
+                    global,
+                    in_channel,
+                    needed
+                );
+                return Ok(Prepared::Respond(MessageResult::None));
+            }
+
+            if chained && !cmd.chainable() {
+                return Ok(Prepared::Respond(MessageResult::Error(format!(
+                    "Command `{}` is not chainable",
+                    cmd.name()
+                ))));
+            }
+
+            pipeline.push((cmd, stage.args));
+        }
+
+        if !exempt {
+            let uses: Vec<_> = pipeline
+                .iter()
+                .map(|(cmd, _)| (cmd.name(), cmd.cooldowns()))
+                .collect();
+
+            if let Some((name, remaining)) =
+                self.cooldowns
+                    .try_start(&uses, msg.channel(), user.id, Instant::now())
+            {
+                debug!(
+                    "Command is on cooldown (name: {}, remaining: {:?})",
+                    name, remaining
+                );
+                return Ok(Prepared::Respond(MessageResult::None));
+            }
+        }
+
+        Ok(Prepared::Run(pipeline))
+    }
+}
+
+/// A single command of a pipeline.
+struct Stage {
+    name: String,
+    command: Option<Arc<Command>>,
+    args: Vec<String>,
+}
+
+/// Parse a segment of a pipeline. A segment may end with a nested command:
+/// `voicemail me in 1h ~ping` is the same as `ping | ~voicemail me in 1h`.
+///
+/// The nested command has to be the last word and both commands have to be chainable. Otherwise
+/// it is a literal argument, like in `quote add try ~ping` or `tell bob check ~ping later`.
+fn parse_segment(
+    segment: &str,
+    prefix: &str,
+    get: &dyn Fn(&str) -> Option<Arc<Command>>,
+) -> Vec<Stage> {
+    let outer = stage(&split_words(segment), get);
+
+    let chainable = outer.command.as_ref().map_or(false, |c| c.chainable());
+
+    let nested = split_nested(segment, prefix)
+        .filter(|_| chainable)
+        .and_then(|(rest, name)| get(name).map(|cmd| (rest, cmd)))
+        .filter(|(_, cmd)| cmd.chainable());
+
+    match nested {
+        Some((rest, cmd)) => vec![
+            Stage {
+                name: cmd.name().to_owned(),
+                command: Some(cmd),
+                args: Vec::new(),
+            },
+            stage(&split_words(rest), get),
+        ],
+        None => vec![outer],
+    }
+}
+
+/// Split a segment that ends with the prefix and a single word into the rest and that word. The
+/// prefix is matched against the segment, so prefixes with whitespace like `chb ` work as well.
+fn split_nested<'a>(segment: &'a str, prefix: &str) -> Option<(&'a str, &'a str)> {
+    segment
+        .rmatch_indices(prefix)
+        .map(|(i, _)| (&segment[..i], &segment[i + prefix.len()..]))
+        .find(|(rest, name)| {
+            // the prefix has to start a word and follow the outer command
+            rest.ends_with(char::is_whitespace)
+                && !rest.trim().is_empty()
+                && !name.is_empty()
+                && !name.contains(char::is_whitespace)
+        })
+}
+
+fn split_words(s: &str) -> Vec<String> {
+    s.split_whitespace().map(String::from).collect()
+}
+
+fn stage(words: &[String], get: &dyn Fn(&str) -> Option<Arc<Command>>) -> Stage {
+    let name = words.get(0).cloned().unwrap_or_default();
+
+    Stage {
+        command: get(&name),
+        name,
+        args: words.get(1..).map(<[String]>::to_vec).unwrap_or_default(),
+    }
+}
+
+/// Split a line into the segments of a pipeline.
+///
+/// Segments are separated by a `|` that is followed by the prefix. Any other `|` is part of the
+/// arguments, for example in `~math true || false`.
+fn split_pipeline<'a>(line: &'a str, prefix: &str) -> Vec<&'a str> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+
+    for (i, _) in line.match_indices('|') {
+        if i < start {
+            continue;
+        }
+
+        if let Some(next) = line[i + 1..].trim_start().strip_prefix(prefix) {
+            segments.push(line[start..i].trim());
+            start = line.len() - next.len();
+        }
+    }
+
+    segments.push(line[start..].trim());
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get(name: &str) -> Option<Arc<Command>> {
+        let builder = Command::with_name(match name {
+            "ping" => "ping",
+            "tell" => "tell",
+            "quote" => "quote",
+            _ => return None,
+        });
+
+        let builder = if name == "quote" {
+            builder
+        } else {
+            builder.chainable()
+        };
+
+        Some(
+            builder
+                .command(|_context, _args, _msg, _user| Ok(MessageResult::None))
+                .done(),
+        )
+    }
+
+    fn stages(segment: &str) -> Vec<(String, Vec<String>)> {
+        parse_segment(segment, "~", &get)
+            .into_iter()
+            .map(|s| (s.name, s.args))
+            .collect()
+    }
+
+    fn words(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_segment() {
+        assert_eq!(
+            stages("tell me in 1h ~ping"),
+            vec![
+                (String::from("ping"), vec![]),
+                (String::from("tell"), words("me in 1h"))
+            ]
+        );
+
+        // quote is not chainable
+        assert_eq!(
+            stages("quote add \"try ~ping\" - bob"),
+            vec![(String::from("quote"), words("add \"try ~ping\" - bob"))]
+        );
+        assert_eq!(
+            stages("quote add try ~ping - bob"),
+            vec![(String::from("quote"), words("add try ~ping - bob"))]
+        );
+        assert_eq!(
+            stages("quote add ~ping"),
+            vec![(String::from("quote"), words("add ~ping"))]
+        );
+        assert_eq!(
+            stages("tell bob try ~quote"),
+            vec![(String::from("tell"), words("bob try ~quote"))]
+        );
+
+        // a nested command has to be the last word
+        assert_eq!(
+            stages("tell bob check ~ping later"),
+            vec![(String::from("tell"), words("bob check ~ping later"))]
+        );
+
+        // prefixes can contain whitespace
+        let stages = |segment| {
+            parse_segment(segment, "chb ", &get)
+                .into_iter()
+                .map(|s| (s.name, s.args))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            stages("tell me in 1h chb ping"),
+            vec![
+                (String::from("ping"), vec![]),
+                (String::from("tell"), words("me in 1h"))
+            ]
+        );
+        assert_eq!(
+            stages("tell me in 1h chb ping later"),
+            vec![(String::from("tell"), words("me in 1h chb ping later"))]
+        );
+        assert_eq!(
+            stages("tell me in 1h chbping"),
+            vec![(String::from("tell"), words("me in 1h chbping"))]
+        );
+    }
+
+    #[test]
+    fn test_split_pipeline() {
+        assert_eq!(split_pipeline("ping", "~"), vec!["ping"]);
+
+        assert_eq!(
+            split_pipeline("color | ~math 1 + 1", "~"),
+            vec!["color", "math 1 + 1"]
+        );
+
+        assert_eq!(
+            split_pipeline("math true || false", "~"),
+            vec!["math true || false"]
+        );
+
+        assert_eq!(
+            split_pipeline("ping |chb test | chb tell me", "chb "),
+            vec!["ping", "test", "tell me"]
+        );
     }
 }
@@ -1,3 +1,4 @@
+pub mod arguments;
 pub mod command;
 
 mod commands;
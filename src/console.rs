@@ -0,0 +1,136 @@
+//! Local console transport
+//!
+//! Reads lines from stdin and handles them like chat messages of a chosen user in a chosen
+//! channel. Replies are printed to stdout, so the bot can be tried without a Twitch connection.
+use crate::{
+    context::BotContext,
+    database::{self, Channel, User},
+    handler::Twitch,
+    helpers::blocking,
+    message::{ConsoleMessage, Message},
+};
+use chrono::prelude::*;
+use snafu::{ResultExt, Snafu};
+use std::sync::Arc;
+use tokio::io::{stdin, AsyncBufReadExt, BufReader};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Reading from stdin: {}", source))]
+    ReadStdin { source: std::io::Error },
+
+    #[snafu(display("Running blocking task: {}", source))]
+    Blocking { source: tokio::task::JoinError },
+
+    #[snafu(display("Getting user (name: {}): {}", name, source))]
+    GetUser {
+        name: String,
+        source: database::user::Error,
+    },
+
+    #[snafu(display("Joining channel (name: {}): {}", name, source))]
+    JoinChannel {
+        name: String,
+        source: database::channel::Error,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+pub struct Console {
+    context: Arc<BotContext>,
+    handlers: Arc<[Arc<dyn Twitch>]>,
+    user: String,
+    channel: String,
+    moderator: bool,
+}
+
+impl Console {
+    pub fn new(
+        context: Arc<BotContext>,
+        handlers: Arc<[Arc<dyn Twitch>]>,
+        user: &str,
+        channel: &str,
+    ) -> Self {
+        Self {
+            context,
+            handlers,
+            user: user.to_lowercase(),
+            channel: channel.trim_start_matches('#').to_lowercase(),
+            moderator: false,
+        }
+    }
+
+    /// Send all messages as a moderator.
+    pub fn moderator(mut self, moderator: bool) -> Self {
+        self.moderator = moderator;
+        self
+    }
+
+    /// Read lines from stdin until it is closed.
+    pub async fn run(&self) -> Result<()> {
+        let user = self.prepare().await?;
+
+        info!(
+            "Chatting as {} in #{} (moderator: {})",
+            user.name, self.channel, self.moderator
+        );
+
+        let mut lines = BufReader::new(stdin()).lines();
+
+        while let Some(line) = lines.next_line().await.context(ReadStdin)? {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            trace!(
+                "Got chat message (provider: console, channel: {})",
+                self.channel
+            );
+
+            let msg = Message::Console(Arc::new(ConsoleMessage {
+                channel: format!("#{}", self.channel),
+                name: user.name.clone(),
+                text: line.to_owned(),
+                moderator: self.moderator,
+                sent_ts: Utc::now().timestamp_millis() as u64,
+            }));
+
+            for handler in self.handlers.iter() {
+                if let Err(err) = handler.clone().handle(msg.clone(), &user).await {
+                    error!(
+                        "Could not handle message (handler: {}): {:?}",
+                        handler.name(),
+                        err,
+                    );
+                }
+            }
+        }
+
+        info!("Stdin closed");
+
+        Ok(())
+    }
+
+    /// Make sure the user and the channel exist in the database.
+    async fn prepare(&self) -> Result<User> {
+        let context = self.context.clone();
+        let name = self.user.clone();
+        let channel = self.channel.clone();
+
+        blocking(move || {
+            let conn = &context.conn();
+
+            // joining is idempotent and works on a fresh database
+            Channel::join(conn, &channel).context(JoinChannel { name: &channel })?;
+
+            match User::by_name(conn, &name).context(GetUser { name: &name })? {
+                Some(user) => Ok(user),
+                None => User::with_name(conn, &name).context(GetUser { name: &name }),
+            }
+        })
+        .await
+        .context(Blocking)?
+    }
+}
@@ -1,8 +1,14 @@
-use crate::{manpages, voicemail::Scheduler, TwitchBot};
+use crate::{
+    database::{channel, Channel},
+    manpages,
+    voicemail::Scheduler,
+    TwitchBot,
+};
 use config::Config;
 use diesel::r2d2::{ConnectionManager, PooledConnection};
 use std::{
-    sync::Arc,
+    collections::HashMap,
+    sync::{Arc, RwLock},
     time::{Duration, Instant},
 };
 
@@ -27,6 +33,9 @@ pub struct BotContext {
     // manpage index
     manpage_index: Arc<manpages::Index>,
 
+    // command prefix per channel. `None` if the channel uses the default
+    prefixes: Arc<RwLock<HashMap<String, Option<String>>>>,
+
     clock: Instant,
 
     pub version: &'static str,
@@ -46,6 +55,7 @@ impl BotContext {
             twitchbot,
             scheduler: Arc::new(Scheduler::new()),
             manpage_index: Arc::new(manpage_index),
+            prefixes: Arc::new(RwLock::new(HashMap::new())),
             clock: Instant::now(),
             version: env!("CARGO_PKG_VERSION"),
             git_commit: env!("GIT_HASH"),
@@ -68,6 +78,35 @@ impl BotContext {
         self.config.get_str("twitch.name").unwrap()
     }
 
+    /// Get the default command prefix. Channels can overwrite this.
+    pub fn prefix(&self) -> String {
+        self.config.get_str("twitch.prefix").unwrap()
+    }
+
+    /// Get the command prefix of `channel`. The prefix is read from the database once and cached
+    /// afterwards. Use `set_channel_prefix` when it changes.
+    pub fn channel_prefix(&self, channel: &str) -> channel::Result<String> {
+        if let Some(prefix) = self.prefixes.read().unwrap().get(channel) {
+            return Ok(prefix.clone().unwrap_or_else(|| self.prefix()));
+        }
+
+        let prefix = Channel::by_name(&self.conn(), channel)?.and_then(|c| c.prefix);
+        self.prefixes
+            .write()
+            .unwrap()
+            .insert(channel.to_owned(), prefix.clone());
+
+        Ok(prefix.unwrap_or_else(|| self.prefix()))
+    }
+
+    /// Update the cached command prefix of `channel`.
+    pub fn set_channel_prefix(&self, channel: &str, prefix: Option<&str>) {
+        self.prefixes
+            .write()
+            .unwrap()
+            .insert(channel.to_owned(), prefix.map(str::to_owned));
+    }
+
     pub fn twitchbot(&self) -> TwitchBot {
         self.twitchbot.clone()
     }
@@ -77,7 +116,7 @@ impl BotContext {
     }
 
     pub async fn run_scheduler(this: Arc<Self>) {
-        this.scheduler.run(this.clone()).await.unwrap()
+        this.scheduler.run(this.clone()).await
     }
 
     pub fn whatis(
@@ -0,0 +1,161 @@
+//! Cooldowns for commands and actions
+use humantime::format_duration;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// The cooldowns a command or action declares.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Cooldowns {
+    pub user: Option<Duration>,
+    pub channel: Option<Duration>,
+    pub global: Option<Duration>,
+}
+
+impl Cooldowns {
+    /// Render the cooldowns as manpage characteristics.
+    pub fn characteristics(&self) -> Vec<(String, String)> {
+        vec![
+            ("user cooldown", self.user),
+            ("channel cooldown", self.channel),
+            ("global cooldown", self.global),
+        ]
+        .into_iter()
+        .filter_map(|(name, cooldown)| {
+            cooldown.map(|d| (name.to_owned(), format_duration(d).to_string()))
+        })
+        .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash)]
+enum Scope {
+    User(i32),
+    Channel(String),
+    Global,
+}
+
+/// Keeps track of when the cooldowns of commands and actions end.
+#[derive(Debug, Default)]
+pub struct CooldownTracker {
+    ends: Mutex<HashMap<(String, Scope), Instant>>,
+}
+
+impl CooldownTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the cooldowns of all `uses` unless one of them is still on cooldown. Returns the
+    /// name and remaining cooldown of the first one on cooldown, nothing is started then.
+    ///
+    /// Checking and starting happen under one lock so that concurrent messages cannot both pass
+    /// the check.
+    pub fn try_start<'a>(
+        &self,
+        uses: &[(&'a str, &Cooldowns)],
+        channel: &str,
+        user_id: i32,
+        now: Instant,
+    ) -> Option<(&'a str, Duration)> {
+        let mut ends = self.ends.lock().unwrap();
+
+        // forget cooldowns that are over so that the map does not grow forever
+        ends.retain(|_, end| *end > now);
+
+        for &(name, cooldowns) in uses {
+            let remaining = Self::scopes(cooldowns, channel, user_id)
+                .into_iter()
+                .filter_map(|(scope, _)| ends.get(&(name.to_string(), scope)))
+                .map(|end| end.duration_since(now))
+                .max();
+
+            if let Some(remaining) = remaining {
+                return Some((name, remaining));
+            }
+        }
+
+        for &(name, cooldowns) in uses {
+            for (scope, cooldown) in Self::scopes(cooldowns, channel, user_id) {
+                ends.insert((name.to_string(), scope), now + cooldown);
+            }
+        }
+
+        None
+    }
+
+    fn scopes(cooldowns: &Cooldowns, channel: &str, user_id: i32) -> Vec<(Scope, Duration)> {
+        vec![
+            (Scope::User(user_id), cooldowns.user),
+            (Scope::Channel(channel.to_owned()), cooldowns.channel),
+            (Scope::Global, cooldowns.global),
+        ]
+        .into_iter()
+        .filter_map(|(scope, cooldown)| cooldown.map(|d| (scope, d)))
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cooldowns() {
+        let tracker = CooldownTracker::new();
+        let cooldowns = Cooldowns {
+            user: Some(Duration::from_secs(10)),
+            channel: Some(Duration::from_secs(5)),
+            global: None,
+        };
+        let none = Cooldowns::default();
+        let now = Instant::now();
+
+        assert_eq!(
+            tracker.try_start(&[("math", &cooldowns)], "#a", 1, now),
+            None
+        );
+
+        let later = now + Duration::from_secs(3);
+        assert_eq!(
+            tracker.try_start(&[("math", &cooldowns)], "#a", 1, later),
+            Some(("math", Duration::from_secs(7)))
+        );
+        assert_eq!(
+            tracker.try_start(&[("math", &cooldowns)], "#a", 2, later),
+            Some(("math", Duration::from_secs(2)))
+        );
+        assert_eq!(tracker.try_start(&[("ping", &none)], "#a", 1, later), None);
+
+        // nothing is started if one of the uses is on cooldown
+        assert_eq!(
+            tracker.try_start(
+                &[("echo", &cooldowns), ("math", &cooldowns)],
+                "#a",
+                1,
+                later
+            ),
+            Some(("math", Duration::from_secs(7)))
+        );
+        assert_eq!(
+            tracker.try_start(&[("echo", &cooldowns)], "#a", 1, later),
+            None
+        );
+
+        assert_eq!(
+            tracker.try_start(&[("math", &cooldowns)], "#b", 2, later),
+            None
+        );
+
+        let much_later = now + Duration::from_secs(20);
+        assert_eq!(
+            tracker.try_start(&[("math", &cooldowns)], "#a", 1, much_later),
+            None
+        );
+
+        // only the cooldowns started last are left
+        assert_eq!(tracker.ends.lock().unwrap().len(), 2);
+    }
+}
@@ -1,7 +1,7 @@
 use super::{user, Connection, User};
 use crate::schema::*;
 use diesel::prelude::*;
-use snafu::{OptionExt, ResultExt, Snafu};
+use snafu::{ResultExt, Snafu};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
@@ -74,6 +74,70 @@ pub enum Error {
         source: diesel::result::Error,
     },
 
+    #[snafu(display("Setting prefix (id: {}): {}", id, source))]
+    SetPrefix {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Setting paused (id: {}): {}", id, source))]
+    SetPaused {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display(
+        "Getting {} filter (channel_id: {}, name: {}): {}",
+        kind,
+        channel_id,
+        name,
+        source
+    ))]
+    GetFilter {
+        kind: &'static str,
+        channel_id: i32,
+        name: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display(
+        "Setting {} filter (channel_id: {}, name: {}): {}",
+        kind,
+        channel_id,
+        name,
+        source
+    ))]
+    SetFilter {
+        kind: &'static str,
+        channel_id: i32,
+        name: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display(
+        "Getting role (channel_id: {}, user_id: {}): {}",
+        channel_id,
+        user_id,
+        source
+    ))]
+    GetRole {
+        channel_id: i32,
+        user_id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display(
+        "Setting role (channel_id: {}, user_id: {}): {}",
+        channel_id,
+        user_id,
+        source
+    ))]
+    SetRole {
+        channel_id: i32,
+        user_id: i32,
+        source: diesel::result::Error,
+    },
+
     UserError {
         source: super::user::Error,
     },
@@ -94,20 +158,19 @@ pub struct Channel {
     pub twitch_id: Option<i64>,
     pub enabled: bool,
     pub paused: bool,
+    pub prefix: Option<String>,
 }
 
 impl Channel {
-    /// Get a `Channel` by the owners name.
+    /// Get a `Channel` by the owners name. Returns `None` if the owner is unknown or has no
+    /// channel.
     pub fn by_name<'a>(conn: &Connection, name: &'a str) -> Result<Option<Channel>> {
         trace!("Getting channel (name: {})", name);
 
-        let user = User::by_name(conn, name)?.context(UserNotFound { name })?;
-
-        Self::by_id(
-            conn,
-            user.channel_id
-                .context(UserHasNoChannel { user_id: user.id })?,
-        )
+        match User::by_name(conn, name)?.and_then(|user| user.channel_id) {
+            Some(id) => Self::by_id(conn, id),
+            None => Ok(None),
+        }
     }
 
     pub fn by_id(conn: &Connection, id: i32) -> Result<Option<Self>> {
@@ -216,6 +279,216 @@ impl Channel {
 
         Ok(user.name)
     }
+
+    /// Set the command prefix of this channel. `None` resets the prefix to the global default.
+    pub fn set_prefix(&self, conn: &Connection, prefix: Option<&str>) -> Result<()> {
+        debug!("Setting prefix (id: {}, prefix: {:?})", self.id, prefix);
+
+        diesel::update(self)
+            .set(channels::prefix.eq(prefix))
+            .execute(conn)
+            .context(SetPrefix { id: self.id })?;
+
+        Ok(())
+    }
+
+    /// Pause or resume the channel. Paused channels stay joined but commands and actions are
+    /// ignored.
+    pub fn set_paused(&self, conn: &Connection, paused: bool) -> Result<()> {
+        debug!("Setting paused (id: {}, paused: {})", self.id, paused);
+
+        diesel::update(self)
+            .set(channels::paused.eq(paused))
+            .execute(conn)
+            .context(SetPaused { id: self.id })?;
+
+        Ok(())
+    }
+
+    /// Get the command filter for `name` in this channel.
+    ///
+    /// Returns `None` if there is no filter for this command. In that case the default of the
+    /// command should be used.
+    pub fn command_filter(&self, conn: &Connection, name: &str) -> Result<Option<bool>> {
+        trace!(
+            "Getting command filter (channel_id: {}, name: {})",
+            self.id,
+            name
+        );
+
+        channel_command_filters::table
+            .filter(channel_command_filters::channel_id.eq(self.id))
+            .filter(channel_command_filters::name.eq(name))
+            .select(channel_command_filters::enable)
+            .get_result(conn)
+            .optional()
+            .context(GetFilter {
+                kind: "command",
+                channel_id: self.id,
+                name,
+            })
+    }
+
+    /// Get the action filter for `name` in this channel.
+    ///
+    /// Returns `None` if there is no filter for this action. In that case the default of the
+    /// action should be used.
+    pub fn action_filter(&self, conn: &Connection, name: &str) -> Result<Option<bool>> {
+        trace!(
+            "Getting action filter (channel_id: {}, name: {})",
+            self.id,
+            name
+        );
+
+        channel_action_filters::table
+            .filter(channel_action_filters::channel_id.eq(self.id))
+            .filter(channel_action_filters::name.eq(name))
+            .select(channel_action_filters::enable)
+            .get_result(conn)
+            .optional()
+            .context(GetFilter {
+                kind: "action",
+                channel_id: self.id,
+                name,
+            })
+    }
+
+    /// Enable or disable the command `name` in this channel. An existing filter is overwritten.
+    pub fn set_command_filter(&self, conn: &Connection, name: &str, enable: bool) -> Result<()> {
+        debug!(
+            "Setting command filter (channel_id: {}, name: {}, enable: {})",
+            self.id, name, enable
+        );
+
+        let updated = diesel::update(channel_command_filters::table)
+            .filter(channel_command_filters::channel_id.eq(self.id))
+            .filter(channel_command_filters::name.eq(name))
+            .set(channel_command_filters::enable.eq(enable))
+            .execute(conn)
+            .context(SetFilter {
+                kind: "command",
+                channel_id: self.id,
+                name,
+            })?;
+
+        if updated == 0 {
+            diesel::insert_into(channel_command_filters::table)
+                .values(&NewChannelCommandFilter {
+                    channel_id: self.id,
+                    name,
+                    enable,
+                })
+                .execute(conn)
+                .context(SetFilter {
+                    kind: "command",
+                    channel_id: self.id,
+                    name,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Enable or disable the action `name` in this channel. An existing filter is overwritten.
+    pub fn set_action_filter(&self, conn: &Connection, name: &str, enable: bool) -> Result<()> {
+        debug!(
+            "Setting action filter (channel_id: {}, name: {}, enable: {})",
+            self.id, name, enable
+        );
+
+        let updated = diesel::update(channel_action_filters::table)
+            .filter(channel_action_filters::channel_id.eq(self.id))
+            .filter(channel_action_filters::name.eq(name))
+            .set(channel_action_filters::enable.eq(enable))
+            .execute(conn)
+            .context(SetFilter {
+                kind: "action",
+                channel_id: self.id,
+                name,
+            })?;
+
+        if updated == 0 {
+            diesel::insert_into(channel_action_filters::table)
+                .values(&NewChannelActionFilter {
+                    channel_id: self.id,
+                    name,
+                    enable,
+                })
+                .execute(conn)
+                .context(SetFilter {
+                    kind: "action",
+                    channel_id: self.id,
+                    name,
+                })?;
+        }
+
+        Ok(())
+    }
+
+    /// Get the permission level `user_id` has in this channel.
+    ///
+    /// Returns `None` if the user has no role in this channel.
+    pub fn role(&self, conn: &Connection, user_id: i32) -> Result<Option<i16>> {
+        trace!(
+            "Getting role (channel_id: {}, user_id: {})",
+            self.id,
+            user_id
+        );
+
+        channel_roles::table
+            .filter(channel_roles::channel_id.eq(self.id))
+            .filter(channel_roles::user_id.eq(user_id))
+            .select(channel_roles::permission)
+            .get_result(conn)
+            .optional()
+            .context(GetRole {
+                channel_id: self.id,
+                user_id,
+            })
+    }
+
+    /// Set the permission level of `user_id` in this channel. `None` removes the role.
+    pub fn set_role(&self, conn: &Connection, user_id: i32, permission: Option<i16>) -> Result<()> {
+        debug!(
+            "Setting role (channel_id: {}, user_id: {}, permission: {:?})",
+            self.id, user_id, permission
+        );
+
+        let role = channel_roles::table
+            .filter(channel_roles::channel_id.eq(self.id))
+            .filter(channel_roles::user_id.eq(user_id));
+
+        let context = SetRole {
+            channel_id: self.id,
+            user_id,
+        };
+
+        let permission = match permission {
+            Some(p) => p,
+            None => {
+                diesel::delete(role).execute(conn).context(context)?;
+                return Ok(());
+            }
+        };
+
+        let updated = diesel::update(role)
+            .set(channel_roles::permission.eq(permission))
+            .execute(conn)
+            .context(context)?;
+
+        if updated == 0 {
+            diesel::insert_into(channel_roles::table)
+                .values(&NewChannelRole {
+                    channel_id: self.id,
+                    user_id,
+                    permission,
+                })
+                .execute(conn)
+                .context(context)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[derive(Insertable)]
@@ -244,3 +517,27 @@ pub struct ChannelCommandFilter {
     pub command_name: String,
     pub enable_command: bool,
 }
+
+#[derive(Insertable)]
+#[table_name = "channel_action_filters"]
+pub struct NewChannelActionFilter<'a> {
+    pub channel_id: i32,
+    pub name: &'a str,
+    pub enable: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "channel_command_filters"]
+pub struct NewChannelCommandFilter<'a> {
+    pub channel_id: i32,
+    pub name: &'a str,
+    pub enable: bool,
+}
+
+#[derive(Insertable)]
+#[table_name = "channel_roles"]
+pub struct NewChannelRole {
+    pub channel_id: i32,
+    pub user_id: i32,
+    pub permission: i16,
+}
@@ -0,0 +1,130 @@
+use super::{random, Connection, User};
+use crate::schema::*;
+use chrono::prelude::*;
+use diesel::prelude::*;
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Inserting copypasta (name: {}): {}", name, source))]
+    InsertCopypasta {
+        name: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting copypasta (name: {}): {}", name, source))]
+    GetCopypastaByName {
+        name: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting random copypasta: {}", source))]
+    GetRandomCopypasta { source: diesel::result::Error },
+
+    #[snafu(display("Getting all copypastas: {}", source))]
+    GetCopypastas { source: diesel::result::Error },
+
+    #[snafu(display("Updating copypasta (id: {}): {}", id, source))]
+    UpdateCopypasta {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Removing copypasta (id: {}): {}", id, source))]
+    RemoveCopypasta {
+        id: i32,
+        source: diesel::result::Error,
+    },
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Queryable, Identifiable, Associations)]
+#[belongs_to(User, foreign_key = "creator_id")]
+#[table_name = "copypastas"]
+pub struct Copypasta {
+    pub id: i32,
+    pub creator_id: i32,
+    pub created: NaiveDateTime,
+    pub name: String,
+    pub message: String,
+}
+
+impl Copypasta {
+    pub fn new<'a>(
+        conn: &Connection,
+        creator_id: i32,
+        name: &'a str,
+        message: &'a str,
+    ) -> Result<Self> {
+        trace!("Creating new copypasta (name: {})", name);
+
+        diesel::insert_into(copypastas::table)
+            .values(&NewCopypasta {
+                creator_id,
+                created: &Utc::now().naive_utc(),
+                name,
+                message,
+            })
+            .get_result(conn)
+            .context(InsertCopypasta { name })
+    }
+
+    pub fn by_name(conn: &Connection, name: &str) -> Result<Option<Self>> {
+        trace!("Getting copypasta (name: {})", name);
+
+        copypastas::table
+            .filter(copypastas::name.eq(name))
+            .get_result(conn)
+            .optional()
+            .context(GetCopypastaByName { name })
+    }
+
+    pub fn random(conn: &Connection) -> Result<Option<Self>> {
+        trace!("Getting random copypasta");
+
+        copypastas::table
+            .order(random)
+            .first(conn)
+            .optional()
+            .context(GetRandomCopypasta)
+    }
+
+    /// Get all copypastas ordered by name.
+    pub fn all(conn: &Connection) -> Result<Vec<Self>> {
+        trace!("Getting all copypastas");
+
+        copypastas::table
+            .order(copypastas::name.asc())
+            .get_results(conn)
+            .context(GetCopypastas)
+    }
+
+    pub fn update(&self, conn: &Connection, message: &str) -> Result<Self> {
+        trace!("Updating copypasta (id: {})", self.id);
+
+        diesel::update(self)
+            .set(copypastas::message.eq(message))
+            .get_result(conn)
+            .context(UpdateCopypasta { id: self.id })
+    }
+
+    pub fn remove(&self, conn: &Connection) -> Result<()> {
+        trace!("Removing copypasta (id: {})", self.id);
+
+        diesel::delete(self)
+            .execute(conn)
+            .context(RemoveCopypasta { id: self.id })?;
+
+        Ok(())
+    }
+}
+
+#[derive(Insertable)]
+#[table_name = "copypastas"]
+pub struct NewCopypasta<'a> {
+    pub creator_id: i32,
+    pub created: &'a NaiveDateTime,
+    pub name: &'a str,
+    pub message: &'a str,
+}
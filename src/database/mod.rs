@@ -1,11 +1,23 @@
 pub mod channel;
+pub mod copypasta;
+pub mod person;
 pub mod quote;
+pub mod settings;
 pub mod user;
 pub mod voicemail;
 
 pub type Connection = diesel::PgConnection;
 
+no_arg_sql_function!(
+    random,
+    diesel::sql_types::Double,
+    "Represents the SQL RANDOM() function"
+);
+
 pub use channel::*;
+pub use copypasta::*;
+pub use person::*;
 pub use quote::*;
+pub use settings::*;
 pub use user::*;
 pub use voicemail::*;
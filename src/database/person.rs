@@ -0,0 +1,196 @@
+use super::{Connection, User};
+use crate::schema::*;
+use chrono::prelude::*;
+use diesel::prelude::*;
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Getting person (id: {}): {}", id, source))]
+    GetPerson {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Inserting person: {}", source))]
+    InsertPerson { source: diesel::result::Error },
+
+    #[snafu(display("Linking person to user (user_id: {}): {}", user_id, source))]
+    LinkPerson {
+        user_id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Setting birthday (id: {}): {}", id, source))]
+    SetBirthday {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Setting greeted (id: {}): {}", id, source))]
+    SetGreeted {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting birthdays: {}", source))]
+    GetBirthdays { source: diesel::result::Error },
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Queryable, Identifiable, Clone, Debug)]
+#[table_name = "people"]
+pub struct Person {
+    pub id: i32,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+    pub dob: Option<NaiveDate>,
+    pub greeted: Option<NaiveDate>,
+}
+
+impl Person {
+    pub fn by_user(conn: &Connection, user: &User) -> Result<Option<Self>> {
+        let id = match user.person_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        trace!("Getting person (id: {})", id);
+
+        people::table
+            .find(id)
+            .get_result(conn)
+            .optional()
+            .context(GetPerson { id })
+    }
+
+    /// Set or remove the birthday of `user`. The person is created if the user has none.
+    pub fn set_birthday(conn: &Connection, user: &User, dob: Option<NaiveDate>) -> Result<Self> {
+        if let Some(id) = user.person_id {
+            trace!("Setting birthday (id: {})", id);
+
+            return diesel::update(people::table.find(id))
+                .set(people::dob.eq(dob))
+                .get_result(conn)
+                .context(SetBirthday { id });
+        }
+
+        trace!("Creating new person (user_id: {})", user.id);
+
+        let person: Self = diesel::insert_into(people::table)
+            .values(people::dob.eq(dob))
+            .get_result(conn)
+            .context(InsertPerson)?;
+
+        diesel::update(users::table.find(user.id))
+            .set(users::person_id.eq(person.id))
+            .execute(conn)
+            .context(LinkPerson { user_id: user.id })?;
+
+        Ok(person)
+    }
+
+    /// Remember that the birthday greeting was sent on `today`.
+    pub fn set_greeted(&self, conn: &Connection, today: NaiveDate) -> Result<()> {
+        diesel::update(self)
+            .set(people::greeted.eq(today))
+            .execute(conn)
+            .context(SetGreeted { id: self.id })?;
+
+        Ok(())
+    }
+
+    /// Get all people with a birthday whose users opted in to birthday greetings.
+    pub fn birthdays(conn: &Connection) -> Result<Vec<(Self, User)>> {
+        trace!("Getting birthdays");
+
+        users::table
+            .inner_join(people::table)
+            .inner_join(user_settings::table)
+            .filter(user_settings::birthdays.eq(true))
+            .filter(people::dob.is_not_null())
+            .select((people::all_columns, users::all_columns))
+            .load(conn)
+            .context(GetBirthdays)
+    }
+
+    /// Get the opted in users with a birthday in the next `days` days, soonest first.
+    pub fn upcoming(
+        conn: &Connection,
+        today: NaiveDate,
+        days: i64,
+    ) -> Result<Vec<(NaiveDate, User)>> {
+        let mut upcoming: Vec<(NaiveDate, User)> = Self::birthdays(conn)?
+            .into_iter()
+            .filter_map(|(person, user)| Some((next_birthday(person.dob?, today), user)))
+            .filter(|(date, _)| (*date - today).num_days() < days)
+            .collect();
+
+        upcoming.sort_by_key(|(date, _)| *date);
+
+        Ok(upcoming)
+    }
+
+    pub fn is_birthday(&self, today: NaiveDate) -> bool {
+        self.dob
+            .map_or(false, |dob| next_birthday(dob, today) == today)
+    }
+
+    pub fn greeted_on(&self, today: NaiveDate) -> bool {
+        self.greeted == Some(today)
+    }
+}
+
+/// Get the next birthday on or after `today`. Birthdays on the 29th of February are celebrated on
+/// the 28th in common years.
+pub fn next_birthday(dob: NaiveDate, today: NaiveDate) -> NaiveDate {
+    let in_year = |year| {
+        NaiveDate::from_ymd_opt(year, dob.month(), dob.day())
+            .unwrap_or_else(|| NaiveDate::from_ymd(year, 2, 28))
+    };
+
+    let birthday = in_year(today.year());
+
+    if birthday >= today {
+        birthday
+    } else {
+        in_year(today.year() + 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_birthday() {
+        let today = NaiveDate::from_ymd(2020, 6, 9);
+
+        assert_eq!(next_birthday(NaiveDate::from_ymd(1990, 6, 9), today), today);
+        assert_eq!(
+            next_birthday(NaiveDate::from_ymd(1990, 12, 24), today),
+            NaiveDate::from_ymd(2020, 12, 24)
+        );
+        assert_eq!(
+            next_birthday(NaiveDate::from_ymd(1990, 1, 1), today),
+            NaiveDate::from_ymd(2021, 1, 1)
+        );
+
+        // leap day birthdays
+        assert_eq!(
+            next_birthday(
+                NaiveDate::from_ymd(1996, 2, 29),
+                NaiveDate::from_ymd(2020, 1, 1)
+            ),
+            NaiveDate::from_ymd(2020, 2, 29)
+        );
+        assert_eq!(
+            next_birthday(
+                NaiveDate::from_ymd(1996, 2, 29),
+                NaiveDate::from_ymd(2021, 1, 1)
+            ),
+            NaiveDate::from_ymd(2021, 2, 28)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Connection, User};
+use super::{random, Connection, User};
 use crate::schema::*;
 use chrono::prelude::*;
 use diesel::prelude::*;
@@ -30,10 +30,36 @@ pub enum Error {
         id: i32,
         source: diesel::result::Error,
     },
+
+    #[snafu(display("Getting random quote: {}", source))]
+    GetRandomQuote { source: diesel::result::Error },
+
+    #[snafu(display("Searching quotes (text: {}): {}", text, source))]
+    SearchQuotes {
+        text: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting quotes (author: {}): {}", author, source))]
+    GetQuotesByAuthor {
+        author: String,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Counting quotes: {}", source))]
+    CountQuotes { source: diesel::result::Error },
+
+    #[snafu(display("Getting author (name: {}): {}", name, source))]
+    GetAuthor {
+        name: String,
+        source: diesel::result::Error,
+    },
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
+type BoxedQuery<'a> = quotes::BoxedQuery<'a, diesel::pg::Pg>;
+
 #[derive(Queryable, Identifiable, Associations)]
 #[belongs_to(User, foreign_key = "creator_id")]
 #[table_name = "quotes"]
@@ -44,6 +70,10 @@ pub struct Quote {
     pub author: String,
     pub authored: String,
     pub message: String,
+    /// The chatter that said the quote. `author` is kept if the author is not known.
+    pub author_id: Option<i32>,
+    /// The channel the quote was added in. Old quotes have no channel.
+    pub channel_id: Option<i32>,
 }
 
 impl Display for Quote {
@@ -57,9 +87,11 @@ impl Display for Quote {
 }
 
 impl Quote {
+    /// Create a new quote. If the author is a known chatter the quote is linked to them.
     pub fn new<'a>(
         conn: &Connection,
         creator_id: i32,
+        channel_id: Option<i32>,
         author: &'a str,
         authored: &'a str,
         message: &'a str,
@@ -73,6 +105,8 @@ impl Quote {
                 author,
                 authored,
                 message,
+                author_id: author_id(conn, author)?,
+                channel_id,
             })
             .get_result(conn)
             .context(InsertQuote)?;
@@ -90,6 +124,74 @@ impl Quote {
             .context(GetQuoteByID { id })
     }
 
+    /// Get a random quote. If `author` is set only quotes by them are considered. If `channel_id`
+    /// is set only quotes from that channel are considered.
+    pub fn random(
+        conn: &Connection,
+        author: Option<&str>,
+        channel_id: Option<i32>,
+    ) -> Result<Option<Self>> {
+        trace!(
+            "Getting random quote (author: {:?}, channel_id: {:?})",
+            author,
+            channel_id
+        );
+
+        let mut query = quotes::table.into_boxed();
+
+        if let Some(author) = author {
+            query = filter_author(conn, query, author)?;
+        }
+
+        if let Some(channel_id) = channel_id {
+            query = query.filter(quotes::channel_id.eq(channel_id));
+        }
+
+        query
+            .order(random)
+            .first(conn)
+            .optional()
+            .context(GetRandomQuote)
+    }
+
+    /// Search quotes case-insensitively. Every word of `text` has to be in the message or the
+    /// author.
+    pub fn search(conn: &Connection, text: &str) -> Result<Vec<Self>> {
+        trace!("Searching quotes (text: {})", text);
+
+        let mut query = quotes::table.into_boxed();
+
+        for word in text.split_whitespace() {
+            let pattern = format!("%{}%", escape_like(word));
+            query = query.filter(
+                quotes::message
+                    .ilike(pattern.clone())
+                    .or(quotes::author.ilike(pattern)),
+            );
+        }
+
+        query
+            .order(quotes::id.asc())
+            .get_results(conn)
+            .context(SearchQuotes { text })
+    }
+
+    /// Get all quotes by `author`. The name is compared case-insensitively.
+    pub fn by_author(conn: &Connection, author: &str) -> Result<Vec<Self>> {
+        trace!("Getting quotes (author: {})", author);
+
+        filter_author(conn, quotes::table.into_boxed(), author)?
+            .order(quotes::id.asc())
+            .get_results(conn)
+            .context(GetQuotesByAuthor { author })
+    }
+
+    pub fn count(conn: &Connection) -> Result<i64> {
+        trace!("Counting quotes");
+
+        quotes::table.count().get_result(conn).context(CountQuotes)
+    }
+
     pub fn update<'a>(
         &self,
         conn: &Connection,
@@ -104,6 +206,7 @@ impl Quote {
                 author,
                 authored,
                 message,
+                author_id: author_id(conn, author)?,
             })
             .get_result(conn)
             .context(UpdateQuote { id: self.id })?;
@@ -122,6 +225,40 @@ impl Quote {
     }
 }
 
+/// Get the id of the chatter named `name`.
+fn author_id(conn: &Connection, name: &str) -> Result<Option<i32>> {
+    let name = name.trim_start_matches('@').to_lowercase();
+
+    users::table
+        .filter(users::name.eq(&name))
+        .select(users::id)
+        .first(conn)
+        .optional()
+        .context(GetAuthor { name: &name })
+}
+
+/// Only select quotes by `author`. Quotes linked to the chatter match even if the name is spelled
+/// differently.
+fn filter_author<'a>(
+    conn: &Connection,
+    query: BoxedQuery<'a>,
+    author: &str,
+) -> Result<BoxedQuery<'a>> {
+    let pattern = escape_like(author.trim_start_matches('@'));
+
+    Ok(match author_id(conn, author)? {
+        Some(id) => query.filter(quotes::author_id.eq(id).or(quotes::author.ilike(pattern))),
+        None => query.filter(quotes::author.ilike(pattern)),
+    })
+}
+
+/// Escape the wildcards of a LIKE pattern.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
 #[derive(Insertable)]
 #[table_name = "quotes"]
 pub struct NewQuote<'a> {
@@ -130,12 +267,16 @@ pub struct NewQuote<'a> {
     pub author: &'a str,
     pub authored: &'a str,
     pub message: &'a str,
+    pub author_id: Option<i32>,
+    pub channel_id: Option<i32>,
 }
 
 #[derive(AsChangeset)]
 #[table_name = "quotes"]
+#[changeset_options(treat_none_as_null = "true")]
 pub struct EditQuote<'a> {
     pub author: &'a str,
     pub authored: &'a str,
     pub message: &'a str,
+    pub author_id: Option<i32>,
 }
@@ -0,0 +1,148 @@
+use super::{Connection, User};
+use crate::{schema::*, timezone::Zone};
+use diesel::prelude::*;
+use snafu::{ResultExt, Snafu};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Getting user settings (id: {}): {}", id, source))]
+    GetSettings {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Inserting user settings: {}", source))]
+    InsertSettings { source: diesel::result::Error },
+
+    #[snafu(display("Linking settings to user (user_id: {}): {}", user_id, source))]
+    LinkSettings {
+        user_id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Updating user settings (id: {}): {}", id, source))]
+    UpdateSettings {
+        id: i32,
+        source: diesel::result::Error,
+    },
+}
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// How voicemails can be delivered.
+pub const VOICEMAIL_DELIVERIES: &[&str] = &["chat", "whisper"];
+
+#[derive(Queryable, Identifiable, Clone, Debug)]
+#[table_name = "user_settings"]
+pub struct UserSettings {
+    pub id: i32,
+    pub birthdays: bool,
+    /// An abbreviation like `CET` or a name from the tz database like `Europe/Berlin`.
+    pub timezone: Option<String>,
+    /// One of `VOICEMAIL_DELIVERIES`.
+    pub voicemail_delivery: String,
+    /// Whether replies mention the user.
+    pub mention: bool,
+    /// An ISO 639-1 language code.
+    pub language: String,
+}
+
+/// The settings of users without a settings row. Keep in sync with the column defaults.
+impl Default for UserSettings {
+    fn default() -> Self {
+        Self {
+            id: 0,
+            birthdays: false,
+            timezone: None,
+            voicemail_delivery: String::from("chat"),
+            mention: true,
+            language: String::from("en"),
+        }
+    }
+}
+
+impl UserSettings {
+    pub fn by_user(conn: &Connection, user: &User) -> Result<Option<Self>> {
+        let id = match user.settings_id {
+            Some(id) => id,
+            None => return Ok(None),
+        };
+
+        user_settings::table
+            .find(id)
+            .get_result(conn)
+            .optional()
+            .context(GetSettings { id })
+    }
+
+    /// Get the settings of `user` or the defaults if the user has none. No row is created.
+    pub fn of(conn: &Connection, user: &User) -> Result<Self> {
+        Ok(Self::by_user(conn, user)?.unwrap_or_default())
+    }
+
+    /// Get the settings of `user`. The row is created with the defaults if the user has none yet.
+    pub fn get_or_create(conn: &Connection, user: &User) -> Result<Self> {
+        if let Some(settings) = Self::by_user(conn, user)? {
+            return Ok(settings);
+        }
+
+        trace!("Creating user settings (user_id: {})", user.id);
+
+        let settings: Self = diesel::insert_into(user_settings::table)
+            .default_values()
+            .get_result(conn)
+            .context(InsertSettings)?;
+
+        diesel::update(users::table.find(user.id))
+            .set(users::settings_id.eq(settings.id))
+            .execute(conn)
+            .context(LinkSettings { user_id: user.id })?;
+
+        Ok(settings)
+    }
+
+    /// Get the home timezone. Returns `None` if it is not set.
+    pub fn zone(&self) -> Option<Zone> {
+        self.timezone.as_deref().and_then(Zone::parse)
+    }
+
+    /// Whether voicemails should be whispered instead of sent in chat.
+    pub fn whisper_voicemails(&self) -> bool {
+        self.voicemail_delivery == "whisper"
+    }
+
+    pub fn set_birthdays(&self, conn: &Connection, birthdays: bool) -> Result<Self> {
+        diesel::update(self)
+            .set(user_settings::birthdays.eq(birthdays))
+            .get_result(conn)
+            .context(UpdateSettings { id: self.id })
+    }
+
+    pub fn set_timezone(&self, conn: &Connection, timezone: Option<&str>) -> Result<Self> {
+        diesel::update(self)
+            .set(user_settings::timezone.eq(timezone))
+            .get_result(conn)
+            .context(UpdateSettings { id: self.id })
+    }
+
+    pub fn set_voicemail_delivery(&self, conn: &Connection, delivery: &str) -> Result<Self> {
+        diesel::update(self)
+            .set(user_settings::voicemail_delivery.eq(delivery))
+            .get_result(conn)
+            .context(UpdateSettings { id: self.id })
+    }
+
+    pub fn set_mention(&self, conn: &Connection, mention: bool) -> Result<Self> {
+        diesel::update(self)
+            .set(user_settings::mention.eq(mention))
+            .get_result(conn)
+            .context(UpdateSettings { id: self.id })
+    }
+
+    pub fn set_language(&self, conn: &Connection, language: &str) -> Result<Self> {
+        diesel::update(self)
+            .set(user_settings::language.eq(language))
+            .get_result(conn)
+            .context(UpdateSettings { id: self.id })
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Channel, Connection, Voicemail};
+use super::{Channel, Connection, Person, UserSettings, Voicemail};
 use crate::schema::*;
 use chrono::prelude::*;
 use diesel::prelude::*;
@@ -48,24 +48,36 @@ pub enum Error {
         source: diesel::result::Error,
     },
 
+    #[snafu(display("Setting permission (id: {}): {}", id, source))]
+    SetPermission {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
     SetVoicemailToInactive {
         id: i32,
         source: diesel::result::Error,
     },
-}
 
-pub type Result<T> = std::result::Result<T, Error>;
+    #[snafu(display("Banning user (id: {}): {}", id, source))]
+    BanUser {
+        id: i32,
+        source: diesel::result::Error,
+    },
 
-#[derive(Queryable, Identifiable)]
-#[table_name = "people"]
-pub struct Person {
-    pub id: i32,
-    pub first_name: Option<String>,
-    pub last_name: Option<String>,
-    pub dob: Option<NaiveDateTime>,
+    #[snafu(display("Unbanning user (id: {}): {}", id, source))]
+    UnbanUser {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting banned users: {}", source))]
+    GetBannedUsers { source: diesel::result::Error },
 }
 
-#[derive(Queryable, Identifiable, Associations)]
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Queryable, Identifiable, Associations, Clone, Debug)]
 #[belongs_to(Person)]
 #[belongs_to(Channel)]
 #[belongs_to(UserSettings, foreign_key = "settings_id")]
@@ -83,6 +95,9 @@ pub struct User {
     pub person_id: Option<i32>,
     pub channel_id: Option<i32>,
     pub settings_id: Option<i32>,
+    pub banned_at: Option<NaiveDateTime>,
+    pub banned_by: Option<i32>,
+    pub ban_reason: Option<String>,
 }
 
 impl User {
@@ -215,11 +230,83 @@ impl User {
         Ok(vms)
     }
 
+    /// Check if the user is banned. Temporary bans expire on their own.
     pub fn banned(&self, now: &DateTime<Local>) -> bool {
-        match self.banned_until {
-            None => false,
-            Some(until) => now.naive_utc() < until,
-        }
+        self.banned_at.is_some()
+            && self
+                .banned_until
+                .map_or(true, |until| now.naive_utc() < until)
+    }
+
+    /// Ban the user until `until` or permanently if `until` is `None`.
+    pub fn ban(
+        &self,
+        conn: &Connection,
+        banner: &User,
+        until: Option<&NaiveDateTime>,
+        reason: Option<&str>,
+        now: &DateTime<Local>,
+    ) -> Result<Self> {
+        debug!(
+            "Banning user (id: {}, banner: {}, until: {:?})",
+            self.id, banner.id, until
+        );
+
+        diesel::update(self)
+            .set(&SetBan {
+                banned_at: Some(&now.naive_utc()),
+                banned_until: until,
+                banned_by: Some(banner.id),
+                ban_reason: reason,
+            })
+            .get_result(conn)
+            .context(BanUser { id: self.id })
+    }
+
+    pub fn unban(&self, conn: &Connection) -> Result<Self> {
+        debug!("Unbanning user (id: {})", self.id);
+
+        diesel::update(self)
+            .set(&SetBan {
+                banned_at: None,
+                banned_until: None,
+                banned_by: None,
+                ban_reason: None,
+            })
+            .get_result(conn)
+            .context(UnbanUser { id: self.id })
+    }
+
+    /// Get all users with a ban that has not expired yet.
+    pub fn bans(conn: &Connection, now: &DateTime<Local>) -> Result<Vec<Self>> {
+        trace!("Getting banned users");
+
+        users::table
+            .filter(
+                users::banned_at.is_not_null().and(
+                    users::banned_until
+                        .is_null()
+                        .or(users::banned_until.gt(now.naive_utc())),
+                ),
+            )
+            .order(users::banned_at.asc())
+            .get_results(conn)
+            .context(GetBannedUsers)
+    }
+
+    /// Set the global permission level of the user.
+    pub fn set_permission(&self, conn: &Connection, permission: i16) -> Result<()> {
+        debug!(
+            "Setting permission (id: {}, permission: {})",
+            self.id, permission
+        );
+
+        diesel::update(self)
+            .set(users::permission.eq(permission))
+            .execute(conn)
+            .context(SetPermission { id: self.id })?;
+
+        Ok(())
     }
 
     pub fn display_name_or_name(&self) -> String {
@@ -263,9 +350,12 @@ pub struct BumpUser<'a> {
     pub last_seen: &'a NaiveDateTime,
 }
 
-#[derive(Queryable, Identifiable, Associations)]
-#[table_name = "user_settings"]
-pub struct UserSettings {
-    pub id: i32,
-    pub birthdays: bool,
+#[derive(AsChangeset)]
+#[table_name = "users"]
+#[changeset_options(treat_none_as_null = "true")]
+pub struct SetBan<'a> {
+    pub banned_at: Option<&'a NaiveDateTime>,
+    pub banned_until: Option<&'a NaiveDateTime>,
+    pub banned_by: Option<i32>,
+    pub ban_reason: Option<&'a str>,
 }
@@ -32,17 +32,6 @@ pub enum Error {
         source: user::Error,
     },
 
-    #[snafu(display("Getting creator (twitch_id: {}): {}", twitch_id, source))]
-    GetCreatorByTwitchID {
-        twitch_id: i64,
-        source: user::Error,
-    },
-
-    #[snafu(display("Creator not found (twitch_id: {})", twitch_id))]
-    CreatorNotFoundTID {
-        twitch_id: i64,
-    },
-
     #[snafu(display("Creator not found (id: {})", id))]
     CreatorNotFoundID {
         id: i32,
@@ -74,6 +63,30 @@ pub enum Error {
     UpdateActiveVoicemail {
         source: diesel::result::Error,
     },
+
+    #[snafu(display("Getting pending voicemails (creator_id: {}): {}", creator_id, source))]
+    GetPendingVoicemails {
+        creator_id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Counting voicemails (receiver_id: {}): {}", receiver_id, source))]
+    CountVoicemails {
+        receiver_id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Updating voicemail message (id: {}): {}", id, source))]
+    UpdateMessage {
+        id: i32,
+        source: diesel::result::Error,
+    },
+
+    #[snafu(display("Getting held voicemails (channel_id: {}): {}", channel_id, source))]
+    GetHeldVoicemails {
+        channel_id: i32,
+        source: diesel::result::Error,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -94,19 +107,18 @@ pub struct Voicemail {
 }
 
 impl Voicemail {
+    /// Insert a voicemail for every recipient. `scheduled` is the resolved schedule in UTC.
     pub fn new(
         conn: &Connection,
         parsed_voicemail: &ParsedVoicemail,
-        twitch_id: i64,
+        creator: &User,
         channel_id: i32,
         now: NaiveDateTime,
+        scheduled: Option<NaiveDateTime>,
     ) -> Result<Vec<Voicemail>> {
         trace!("Creating new voicemails");
 
         let mut new_voicemails: Vec<NewVoicemail> = Vec::new();
-        let creator = User::by_twitch_id(conn, twitch_id)
-            .context(GetCreatorByTwitchID { twitch_id })?
-            .context(CreatorNotFoundTID { twitch_id })?;
 
         for name in &parsed_voicemail.recipients {
             let receiver = match User::by_name(conn, name).context(GetReceiverByName { name })? {
@@ -119,7 +131,7 @@ impl Voicemail {
                 receiver_id: receiver.id,
                 channel_id,
                 created: now,
-                scheduled: parsed_voicemail.schedule,
+                scheduled,
                 message: parsed_voicemail.message.clone(),
             })
         }
@@ -154,6 +166,51 @@ impl Voicemail {
             .context(GetActiveScheduledVoicemails)
     }
 
+    /// Get the active voicemails in a channel that are due. These are held while the channel is
+    /// paused.
+    pub fn held(conn: &Connection, channel_id: i32, now: NaiveDateTime) -> Result<Vec<Voicemail>> {
+        voicemails::table
+            .filter(
+                voicemails::active
+                    .eq(true)
+                    .and(voicemails::channel_id.eq(channel_id))
+                    .and(voicemails::scheduled.le(now)),
+            )
+            .get_results(conn)
+            .context(GetHeldVoicemails { channel_id })
+    }
+
+    /// Get the active voicemails sent by `creator_id`, oldest first.
+    pub fn pending(conn: &Connection, creator_id: i32) -> Result<Vec<Voicemail>> {
+        voicemails::table
+            .filter(
+                voicemails::active
+                    .eq(true)
+                    .and(voicemails::creator_id.eq(creator_id)),
+            )
+            .order(voicemails::id)
+            .get_results(conn)
+            .context(GetPendingVoicemails { creator_id })
+    }
+
+    /// Count the active voicemails for `receiver_id` that are not scheduled after `now`.
+    pub fn count_inbox(conn: &Connection, receiver_id: i32, now: NaiveDateTime) -> Result<i64> {
+        voicemails::table
+            .filter(
+                voicemails::active
+                    .eq(true)
+                    .and(voicemails::receiver_id.eq(receiver_id))
+                    .and(
+                        voicemails::scheduled
+                            .is_null()
+                            .or(voicemails::scheduled.le(now)),
+                    ),
+            )
+            .count()
+            .get_result(conn)
+            .context(CountVoicemails { receiver_id })
+    }
+
     pub fn to_string(&self, conn: &Connection) -> String {
         match Self::format(conn, self) {
             Ok(s) => s,
@@ -172,6 +229,15 @@ impl Voicemail {
             .map(|_| ())
     }
 
+    pub fn set_message(&self, conn: &Connection, message: &str) -> Result<Voicemail> {
+        trace!("Updating voicemail message (id: {})", self.id);
+
+        diesel::update(self)
+            .set(voicemails::message.eq(message))
+            .get_result(conn)
+            .context(UpdateMessage { id: self.id })
+    }
+
     fn format(conn: &Connection, voicemail: &Voicemail) -> Result<String> {
         let creator = User::by_id(conn, voicemail.creator_id)
             .context(GetCreatorByID {
@@ -0,0 +1,53 @@
+use crate::handler::{SimpleHandler, TwitchEvents};
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use twitchchat::messages::{ClearChat, Notice, RoomState, UserNotice};
+
+/// Write every event to the log.
+pub struct EventLogger;
+
+impl SimpleHandler for EventLogger {
+    fn name(&self) -> &str {
+        "event logger"
+    }
+}
+
+#[async_trait]
+impl TwitchEvents for EventLogger {
+    async fn user_notice(self: Arc<Self>, msg: Arc<UserNotice<'static>>) -> Result<()> {
+        info!(
+            "Got user notice (channel: {}, type: {}): {}",
+            msg.channel,
+            msg.tags.get("msg-id").map_or("unknown", |v| &**v),
+            msg.tags.get("system-msg").map_or("", |v| &**v),
+        );
+
+        Ok(())
+    }
+
+    async fn clear_chat(self: Arc<Self>, msg: Arc<ClearChat<'static>>) -> Result<()> {
+        match (&msg.name, msg.tags.get("ban-duration")) {
+            (Some(name), Some(duration)) => info!(
+                "User timed out (channel: {}, name: {}, duration: {}s)",
+                msg.channel, name, duration
+            ),
+            (Some(name), None) => info!("User banned (channel: {}, name: {})", msg.channel, name),
+            (None, _) => info!("Chat cleared (channel: {})", msg.channel),
+        }
+
+        Ok(())
+    }
+
+    async fn room_state(self: Arc<Self>, msg: Arc<RoomState<'static>>) -> Result<()> {
+        debug!("Got room state (channel: {})", msg.channel);
+
+        Ok(())
+    }
+
+    async fn notice(self: Arc<Self>, msg: Arc<Notice<'static>>) -> Result<()> {
+        info!("Got notice (channel: {}): {}", msg.channel, msg.message);
+
+        Ok(())
+    }
+}
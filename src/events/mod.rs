@@ -0,0 +1,4 @@
+//! Handlers for Twitch events that are not chat messages
+mod logger;
+
+pub use logger::EventLogger;
@@ -1,8 +1,11 @@
-use crate::{database::User, message::MessageConsumer};
+use crate::{
+    database::User,
+    message::{Message, MessageConsumer},
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
-use twitchchat::messages::Privmsg;
+use twitchchat::messages::{ClearChat, Notice, RoomState, UserNotice};
 
 pub trait Handler<T>: Twitch + Send + Sync
 where
@@ -13,7 +16,33 @@ where
 
 #[async_trait]
 pub trait Twitch: SimpleHandler + Send + Sync {
-    async fn handle(&self, msg: Arc<Privmsg<'_>>, user: &User) -> Result<()>;
+    /// Handle an incoming chat message. The message can come from any transport.
+    async fn handle(self: Arc<Self>, msg: Message<'static>, user: &User) -> Result<()>;
+}
+
+/// Handles Twitch events that are not chat messages. Every event is ignored by default, so
+/// handlers only implement what they are interested in.
+#[async_trait]
+pub trait TwitchEvents: SimpleHandler + Send + Sync {
+    /// Subs, resubs, gift subs, raids and rituals.
+    async fn user_notice(self: Arc<Self>, _msg: Arc<UserNotice<'static>>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Timeouts, bans and cleared chats.
+    async fn clear_chat(self: Arc<Self>, _msg: Arc<ClearChat<'static>>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Changes of room modes like slow mode or emote only.
+    async fn room_state(self: Arc<Self>, _msg: Arc<RoomState<'static>>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Notices sent by TMI, for example when a command failed.
+    async fn notice(self: Arc<Self>, _msg: Arc<Notice<'static>>) -> Result<()> {
+        Ok(())
+    }
 }
 
 pub trait SimpleHandler {
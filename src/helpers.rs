@@ -1,27 +1,43 @@
 use crate::{
-    database::{user, User},
+    database::{channel, user, Channel, Connection, User},
     message::Message,
 };
-use snafu::Snafu;
-use std::time::Duration;
+use snafu::{ResultExt, Snafu};
+use std::{fmt, time::Duration};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
-    GetUser { source: user::Error },
+    GetUser {
+        source: user::Error,
+    },
     GetIDFromMessage,
-    ConvertUserID { source: std::num::TryFromIntError },
+    ConvertUserID {
+        source: std::num::TryFromIntError,
+    },
     UserNotFound,
+
+    #[snafu(display("Getting channel: {}", source))]
+    GetChannel {
+        source: channel::Error,
+    },
+
+    #[snafu(display("Getting role: {}", source))]
+    GetRole {
+        source: channel::Error,
+    },
 }
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, PartialEq, PartialOrd)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Permission {
     // Lowest
     Unknown,
     User,
-    Broadcaster,
+    Subscriber,
+    Vip,
     Moderator,
+    Broadcaster,
     Friend,
     Owner,
     // Highest
@@ -32,35 +48,166 @@ impl From<i16> for Permission {
         match v {
             x if x >= 1337 => Self::Owner,
             x if x > 100 => Self::Friend,
+            x if x >= 40 => Self::Broadcaster,
+            x if x >= 30 => Self::Moderator,
+            x if x >= 20 => Self::Vip,
+            x if x >= 10 => Self::Subscriber,
+            x if x >= 1 => Self::User,
             _ => Self::Unknown,
         }
     }
 }
 
+impl From<Permission> for i16 {
+    fn from(p: Permission) -> Self {
+        match p {
+            Permission::Unknown => 0,
+            Permission::User => 1,
+            Permission::Subscriber => 10,
+            Permission::Vip => 20,
+            Permission::Moderator => 30,
+            Permission::Broadcaster => 40,
+            Permission::Friend => 101,
+            Permission::Owner => 1337,
+        }
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Unknown => "unknown",
+            Self::User => "user",
+            Self::Subscriber => "subscriber",
+            Self::Vip => "vip",
+            Self::Moderator => "moderator",
+            Self::Broadcaster => "broadcaster",
+            Self::Friend => "friend",
+            Self::Owner => "owner",
+        };
+
+        f.write_str(name)
+    }
+}
+
 impl Permission {
-    pub fn from_user(msg: Message, user: &User) -> Result<Self> {
-        let permission: Permission = user.permission.into();
+    /// Parse a role name like `vip` or `mod`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "user" => Some(Self::User),
+            "sub" | "subscriber" => Some(Self::Subscriber),
+            "vip" => Some(Self::Vip),
+            "mod" | "moderator" => Some(Self::Moderator),
+            "broadcaster" => Some(Self::Broadcaster),
+            "friend" => Some(Self::Friend),
+            "owner" => Some(Self::Owner),
+            _ => None,
+        }
+    }
+
+    /// Whether the role only exists in a channel, like `moderator`.
+    pub fn is_channel_role(self) -> bool {
+        matches!(
+            self,
+            Self::Subscriber | Self::Vip | Self::Moderator | Self::Broadcaster
+        )
+    }
+
+    /// Whether the role only exists globally, like `friend`.
+    pub fn is_global_role(self) -> bool {
+        matches!(self, Self::Friend | Self::Owner)
+    }
+
+    /// Get the permission a Twitch badge grants.
+    pub fn from_badge(badge: &str) -> Option<Self> {
+        match badge {
+            "broadcaster" => Some(Self::Broadcaster),
+            "moderator" => Some(Self::Moderator),
+            "vip" => Some(Self::Vip),
+            "subscriber" | "founder" => Some(Self::Subscriber),
+            _ => None,
+        }
+    }
+
+    /// Get the permission of `user` in the channel `msg` was sent in. This is the highest of the
+    /// global role, the role in the channel and the badges.
+    pub fn from_user(conn: &Connection, msg: &Message, user: &User) -> Result<Self> {
+        Ok(Self::global(user).max(Self::in_channel(conn, msg, user)?))
+    }
+
+    /// Get the global role of `user`.
+    pub fn global(user: &User) -> Self {
+        Self::from(user.permission).max(Self::User)
+    }
+
+    /// Get the permission of `user` in the channel `msg` was sent in, ignoring the global role.
+    /// This is the highest of the role in the channel and the badges.
+    pub fn in_channel(conn: &Connection, msg: &Message, user: &User) -> Result<Self> {
+        let mut permission = Self::User;
 
-        if permission != Permission::Unknown {
-            return Ok(permission);
+        let channel_name = msg.channel().trim_start_matches('#');
+
+        if channel_name == msg.name() {
+            permission = permission.max(Self::Broadcaster);
         }
 
-        match msg {
-            Message::TwitchPrivmsg(msg) => {
-                if msg.channel == msg.name {
-                    return Ok(Permission::Broadcaster);
-                }
+        for badge in msg.badges() {
+            if let Some(p) = Self::from_badge(badge) {
+                permission = permission.max(p);
+            }
+        }
 
-                if msg.is_moderator() {
-                    return Ok(Permission::Moderator);
-                }
+        if let Some(channel) = Channel::by_name(conn, channel_name).context(GetChannel)? {
+            if let Some(role) = channel.role(conn, user.id).context(GetRole)? {
+                // global roles only count globally
+                permission = permission.max(Self::from(role).min(Self::Broadcaster));
             }
         }
 
-        Ok(Self::User)
+        Ok(permission)
+    }
+}
+
+/// What a user needs to run a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Requirement {
+    /// The highest of the global and the channel permission has to be at least this.
+    Permission(Permission),
+    /// The user has to be the broadcaster of the channel or an owner of the bot. Friends and
+    /// roles in other channels are not enough.
+    ChannelBroadcasterOrOwner,
+}
+
+impl Requirement {
+    /// Check the requirement against the `global` and the `channel` permission of a user.
+    pub fn is_met(self, global: Permission, channel: Permission) -> bool {
+        match self {
+            Self::Permission(p) => global.max(channel) >= p,
+            Self::ChannelBroadcasterOrOwner => {
+                channel >= Permission::Broadcaster || global >= Permission::Owner
+            }
+        }
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Permission(p) => p.fmt(f),
+            Self::ChannelBroadcasterOrOwner => f.write_str("broadcaster or owner"),
+        }
     }
 }
 
+/// Run blocking code like database queries on the blocking thread pool instead of the executor.
+pub async fn blocking<F, T>(f: F) -> std::result::Result<T, tokio::task::JoinError>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await
+}
+
 pub fn truncate_duration(dur: Duration) -> Duration {
     Duration::from_secs(dur.as_secs())
 }
@@ -72,3 +219,48 @@ pub fn prettify_bool(b: bool) -> &'static str {
         "✘"
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permission_levels() {
+        for p in vec![
+            Permission::Unknown,
+            Permission::User,
+            Permission::Subscriber,
+            Permission::Vip,
+            Permission::Moderator,
+            Permission::Broadcaster,
+            Permission::Friend,
+            Permission::Owner,
+        ] {
+            assert_eq!(Permission::from(i16::from(p)), p);
+            assert_eq!(Permission::from_name(&p.to_string()).unwrap_or(p), p);
+        }
+
+        assert_eq!(Permission::from(9001), Permission::Owner);
+        assert_eq!(Permission::from(200), Permission::Friend);
+        assert!(Permission::Vip < Permission::Moderator);
+
+        assert!(Permission::Moderator.is_channel_role());
+        assert!(!Permission::Moderator.is_global_role());
+        assert!(Permission::Friend.is_global_role());
+        assert!(!Permission::User.is_channel_role() && !Permission::User.is_global_role());
+    }
+
+    #[test]
+    fn test_requirement() {
+        let broadcaster = Requirement::ChannelBroadcasterOrOwner;
+        assert!(broadcaster.is_met(Permission::User, Permission::Broadcaster));
+        assert!(broadcaster.is_met(Permission::Friend, Permission::Broadcaster));
+        assert!(broadcaster.is_met(Permission::Owner, Permission::User));
+        assert!(!broadcaster.is_met(Permission::Friend, Permission::Moderator));
+
+        let moderator = Requirement::Permission(Permission::Moderator);
+        assert!(moderator.is_met(Permission::User, Permission::Moderator));
+        assert!(moderator.is_met(Permission::Friend, Permission::User));
+        assert!(!moderator.is_met(Permission::User, Permission::Vip));
+    }
+}
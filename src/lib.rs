@@ -11,15 +11,19 @@ extern crate log;
 extern crate lazy_static;
 
 pub mod actions;
+pub mod birthdays;
 pub mod commands;
+pub mod console;
 pub mod context;
+pub mod cooldown;
 pub mod database;
+pub mod events;
 pub mod handler;
 pub mod helpers;
 pub mod manpages;
 pub mod message;
-pub mod models;
 pub mod schema;
+pub mod timezone;
 pub mod voicemail;
 
 mod log_format;
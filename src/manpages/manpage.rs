@@ -28,6 +28,7 @@ pub struct Manpage {
     description: String,
     example: Option<String>,
     characteristics: Vec<(String, String)>,
+    usage: Vec<String>,
 }
 
 impl Manpage {
@@ -46,9 +47,16 @@ impl Manpage {
             description,
             example,
             characteristics,
+            usage: Vec::new(),
         }
     }
 
+    /// Set the usage. Every line is one way to call the command.
+    pub fn usage(mut self, usage: Vec<String>) -> Self {
+        self.usage = usage;
+        self
+    }
+
     pub fn name(&self) -> &str {
         self.names.get(0).unwrap()
     }
@@ -115,6 +123,21 @@ impl Manpage {
         )
     }
 
+    fn render_usage(&self) -> String {
+        if self.usage.is_empty() {
+            return String::from("");
+        }
+
+        let usage: Vec<String> = self.usage.iter().map(|l| format!("* {}", l)).collect();
+
+        format!(
+            "== USAGE
+
+{}",
+            usage.join("\n")
+        )
+    }
+
     fn render_description(&self) -> String {
         format!(
             "== DESCRIPTION
@@ -142,6 +165,7 @@ impl Manpage {
             self.render_aliases(),
             self.render_name(),
             self.render_characteristics(),
+            self.render_usage(),
             self.render_description(),
             self.render_example(),
         ];
@@ -1,7 +1,8 @@
 use crate::{context::BotContext, database::User};
 use anyhow::Result;
+use async_trait::async_trait;
 use std::sync::Arc;
-use twitchchat::messages::Privmsg;
+use twitchchat::messages::{Privmsg, Whisper};
 
 pub enum MessageResult {
     None,
@@ -9,47 +10,177 @@ pub enum MessageResult {
     Message(String),
     Error(String),
     MissingArgument(&'static str),
+    /// Whisper to the user even if the command was used in a channel.
+    Whisper(String),
 }
 
+impl MessageResult {
+    /// Convert the result into the text that is sent to chat. Replies are addressed to `user` if
+    /// `mention` is set.
+    pub fn into_text(self, user: &User, mention: bool) -> Option<String> {
+        match self {
+            Self::None => None,
+            Self::Reply(m) if mention => Some(format!("{}, {}", user.display_name_or_name(), m)),
+            Self::Reply(m) => Some(m),
+            Self::Message(m) => Some(m),
+            Self::Error(m) => Some(format!("Error: {}", m)),
+            Self::MissingArgument(a) => Some(format!("Missing argument `{}`", a)),
+            Self::Whisper(m) => Some(m),
+        }
+    }
+}
+
+#[async_trait]
 pub trait MessageConsumer: Send + Sync {
     fn name(&self) -> &str;
     fn whitelisted(&self) -> bool;
 
-    fn consume(
-        &self,
+    /// Execute the consumer. Blocking functions are run on the blocking thread pool.
+    async fn consume(
+        self: Arc<Self>,
         context: Arc<BotContext>,
         args: Vec<String>,
-        msg: Message,
-        user: &User,
+        msg: Message<'static>,
+        user: User,
     ) -> Result<MessageResult>;
 }
 
+/// An incoming chat message. Handlers and consumers should only use the accessors so they work
+/// with every transport.
+#[derive(Clone)]
 pub enum Message<'a> {
     TwitchPrivmsg(Arc<Privmsg<'a>>),
+    TwitchWhisper(WhisperMessage<'a>),
+    Console(Arc<ConsoleMessage>),
 }
 
 impl Message<'_> {
+    /// The channel the message was sent in, including the leading `#`.
     pub fn channel(&self) -> &str {
         match self {
             Self::TwitchPrivmsg(msg) => &msg.channel,
+            Self::TwitchWhisper(msg) => &msg.channel,
+            Self::Console(msg) => &msg.channel,
+        }
+    }
+
+    /// The login name of the sender.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::TwitchPrivmsg(msg) => &msg.name,
+            Self::TwitchWhisper(msg) => &msg.whisper.name,
+            Self::Console(msg) => &msg.name,
+        }
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        match self {
+            Self::TwitchPrivmsg(msg) => msg.display_name().map(|name| name.as_ref()),
+            Self::TwitchWhisper(msg) => msg.whisper.display_name().map(|name| name.as_ref()),
+            Self::Console(msg) => Some(&msg.name),
+        }
+    }
+
+    /// The text of the message.
+    pub fn text(&self) -> &str {
+        match self {
+            Self::TwitchPrivmsg(msg) => &msg.data,
+            Self::TwitchWhisper(msg) => &msg.whisper.data,
+            Self::Console(msg) => &msg.text,
         }
     }
 
     pub fn twitch_id(&self) -> Option<u64> {
         match self {
             Self::TwitchPrivmsg(msg) => msg.user_id(),
+            Self::TwitchWhisper(msg) => msg.whisper.user_id(),
+            Self::Console(_) => None,
         }
     }
 
     pub fn sent_ts(&self) -> u64 {
         match self {
             Self::TwitchPrivmsg(msg) => msg.tmi_sent_ts().unwrap_or(0),
+            Self::TwitchWhisper(msg) => msg.received_ts,
+            Self::Console(msg) => msg.sent_ts,
         }
     }
 
     pub fn color(&self) -> String {
         match self {
             Self::TwitchPrivmsg(msg) => format!("{}", msg.color().unwrap()),
+            Self::TwitchWhisper(msg) => msg
+                .whisper
+                .color()
+                .map(|c| c.to_string())
+                .unwrap_or_default(),
+            Self::Console(_) => String::from("#FFFFFF"),
         }
     }
+
+    /// The names of the badges of the sender, like `moderator` or `subscriber`.
+    pub fn badges(&self) -> Vec<&str> {
+        match self {
+            Self::TwitchPrivmsg(msg) => {
+                parse_badges(msg.tags.get("badges").map(|tag| tag.as_ref()))
+            }
+            Self::TwitchWhisper(msg) => {
+                parse_badges(msg.whisper.tags.get("badges").map(|tag| tag.as_ref()))
+            }
+            Self::Console(msg) if msg.moderator => vec!["moderator"],
+            Self::Console(_) => Vec::new(),
+        }
+    }
+
+    pub fn is_whisper(&self) -> bool {
+        match self {
+            Self::TwitchWhisper(_) => true,
+            _ => false,
+        }
+    }
+}
+
+/// A whisper sent to the bot. Whispers are handled as if they were sent in the bot's channel.
+#[derive(Clone)]
+pub struct WhisperMessage<'a> {
+    pub whisper: Arc<Whisper<'a>>,
+    /// The channel of the bot, including the leading `#`.
+    pub channel: String,
+    /// Unix timestamp in milliseconds. Twitch does not send one for whispers.
+    pub received_ts: u64,
+}
+
+/// Parse the badges tag (`broadcaster/1,subscriber/12`) into the names of the badges.
+fn parse_badges(tag: Option<&str>) -> Vec<&str> {
+    tag.unwrap_or_default()
+        .split(',')
+        .filter_map(|badge| badge.split('/').next())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// A message typed into the local console.
+#[derive(Debug, Clone)]
+pub struct ConsoleMessage {
+    pub channel: String,
+    pub name: String,
+    pub text: String,
+    pub moderator: bool,
+    /// Unix timestamp in milliseconds
+    pub sent_ts: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_badges() {
+        assert_eq!(parse_badges(None), Vec::<&str>::new());
+        assert_eq!(parse_badges(Some("")), Vec::<&str>::new());
+        assert_eq!(
+            parse_badges(Some("broadcaster/1,subscriber/12,founder/0")),
+            vec!["broadcaster", "subscriber", "founder"]
+        );
+    }
 }
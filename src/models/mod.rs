@@ -1,3 +0,0 @@
-mod other;
-
-pub use other::*;
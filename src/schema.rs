@@ -16,12 +16,22 @@ table! {
     }
 }
 
+table! {
+    channel_roles (id) {
+        id -> Int4,
+        channel_id -> Int4,
+        user_id -> Int4,
+        permission -> Int2,
+    }
+}
+
 table! {
     channels (id) {
         id -> Int4,
         twitch_id -> Nullable<Int8>,
         enabled -> Bool,
         paused -> Bool,
+        prefix -> Nullable<Varchar>,
     }
 }
 
@@ -41,6 +51,7 @@ table! {
         first_name -> Nullable<Varchar>,
         last_name -> Nullable<Varchar>,
         dob -> Nullable<Date>,
+        greeted -> Nullable<Date>,
     }
 }
 
@@ -52,6 +63,8 @@ table! {
         author -> Varchar,
         authored -> Varchar,
         message -> Varchar,
+        author_id -> Nullable<Int4>,
+        channel_id -> Nullable<Int4>,
     }
 }
 
@@ -59,6 +72,10 @@ table! {
     user_settings (id) {
         id -> Int4,
         birthdays -> Bool,
+        timezone -> Nullable<Varchar>,
+        voicemail_delivery -> Varchar,
+        mention -> Bool,
+        language -> Varchar,
     }
 }
 
@@ -75,6 +92,9 @@ table! {
         person_id -> Nullable<Int4>,
         channel_id -> Nullable<Int4>,
         settings_id -> Nullable<Int4>,
+        banned_at -> Nullable<Timestamp>,
+        banned_by -> Nullable<Int4>,
+        ban_reason -> Nullable<Text>,
     }
 }
 
@@ -93,15 +113,19 @@ table! {
 
 joinable!(channel_action_filters -> channels (channel_id));
 joinable!(channel_command_filters -> channels (channel_id));
+joinable!(channel_roles -> channels (channel_id));
+joinable!(channel_roles -> users (user_id));
 joinable!(copypastas -> users (creator_id));
-joinable!(quotes -> users (creator_id));
+joinable!(quotes -> channels (channel_id));
 joinable!(users -> channels (channel_id));
+joinable!(users -> people (person_id));
 joinable!(users -> user_settings (settings_id));
 joinable!(voicemails -> channels (channel_id));
 
 allow_tables_to_appear_in_same_query!(
     channel_action_filters,
     channel_command_filters,
+    channel_roles,
     channels,
     copypastas,
     people,
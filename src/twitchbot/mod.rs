@@ -0,0 +1,540 @@
+mod queue;
+
+pub use queue::MAX_TEXT_LENGTH;
+
+use crate::{
+    context::BotContext,
+    database::{self, User},
+    handler::{Twitch, TwitchEvents},
+    helpers::blocking,
+    message::{Message, MessageResult, WhisperMessage},
+    Stopwatch,
+};
+use chrono::prelude::*;
+use queue::Queue;
+use snafu::{OptionExt, ResultExt, Snafu};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    future::Future,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
+use tokio::{
+    stream::StreamExt as _,
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::delay_for,
+};
+use twitchchat::{
+    connect_easy_tls, events, messages, Control, Dispatcher, EventStream, RateLimit, Runner,
+    Status, Writer, TWITCH_IRC_ADDRESS_TLS,
+};
+
+#[derive(Debug, Snafu)]
+pub enum Error {
+    #[snafu(display("Connection to {}: {}", server, source))]
+    Connect {
+        server: &'static str,
+        source: std::io::Error,
+    },
+
+    #[snafu(display("Connection closed before the IRC Ready message"))]
+    WaitForIrcReady,
+
+    #[snafu(display("Running runner: {}", source))]
+    RunRunner { source: twitchchat::Error },
+
+    #[snafu(display("Getting enabled channels: {}", source))]
+    GetEnabledChannels { source: database::channel::Error },
+
+    #[snafu(display("Joining channel (name: {}): {}", channel, source))]
+    JoinChannel {
+        source: twitchchat::Error,
+        channel: String,
+    },
+
+    #[snafu(display("Parting channel (name: {}): {}", channel, source))]
+    PartChannel {
+        source: twitchchat::Error,
+        channel: String,
+    },
+
+    #[snafu(display("Running blocking task: {}", source))]
+    Blocking { source: tokio::task::JoinError },
+
+    #[snafu(display("Bumping user: {}", source))]
+    BumpUser { source: database::user::Error },
+
+    #[snafu(display("Could not get user id from message"))]
+    GetUserID,
+
+    #[snafu(display("Could not get display name from message"))]
+    GetDisplayName,
+
+    #[snafu(display("Converting user id (u64) to i64: {}", source))]
+    ConvertUserID { source: std::num::TryFromIntError },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The first delay before reconnecting.
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The longest delay before reconnecting.
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+
+/// Connections that stayed up this long reset the backoff.
+const STABLE_CONNECTION: Duration = Duration::from_secs(60);
+
+/// The control of the current connection. Every connection needs a new runner, so the control is
+/// replaced on reconnect and shared by all clones of the bot and the queue.
+#[derive(Clone)]
+struct Connection(Arc<RwLock<Control>>);
+
+impl Connection {
+    fn new(control: Control) -> Self {
+        Self(Arc::new(RwLock::new(control)))
+    }
+
+    fn replace(&self, control: Control) {
+        *self.0.write().unwrap() = control;
+    }
+
+    fn writer(&self) -> Writer {
+        let mut control = self.0.read().unwrap().clone();
+        control.writer().clone()
+    }
+
+    fn stop(&self) {
+        self.0.read().unwrap().stop()
+    }
+}
+
+#[derive(Clone)]
+pub struct TwitchBot {
+    connection: Connection,
+    dispatcher: Dispatcher,
+    queue: Queue,
+    reconnects: Arc<AtomicUsize>,
+}
+
+impl TwitchBot {
+    pub fn new() -> (Self, Runner) {
+        let dispatcher = Dispatcher::new();
+        let (runner, control) = Runner::new(dispatcher.clone(), RateLimit::default());
+        let connection = Connection::new(control);
+
+        (
+            Self {
+                queue: Queue::new(connection.clone()),
+                connection,
+                dispatcher,
+                reconnects: Arc::new(AtomicUsize::new(0)),
+            },
+            runner,
+        )
+    }
+
+    /// Like `new` but messages are printed to stdout instead of being sent to Twitch.
+    pub fn console() -> (Self, Runner) {
+        let (mut bot, runner) = Self::new();
+        bot.queue = Queue::stdout();
+
+        (bot, runner)
+    }
+
+    /// Connect to Twitch and handle messages. If the connection is lost the bot reconnects with
+    /// an exponential backoff. Only returns when the bot is stopped.
+    pub async fn start(
+        &self,
+        runner: Runner,
+        context: Arc<BotContext>,
+        name: String,
+        token: String,
+        handlers: Arc<[Arc<dyn Twitch>]>,
+        event_handlers: Arc<[Arc<dyn TwitchEvents>]>,
+    ) -> Result<Status> {
+        let bot = self.run(context.clone(), handlers, event_handlers);
+        let connection = self.connect(runner, context, name, token);
+
+        tokio::select! {
+            _ = bot => { warn!("bot stopped"); Ok(Status::Canceled) }
+            status = connection => status,
+        }
+    }
+
+    /// Keep the connection alive. Outgoing messages are kept in the queue while reconnecting.
+    async fn connect(
+        &self,
+        mut runner: Runner,
+        context: Arc<BotContext>,
+        name: String,
+        token: String,
+    ) -> Result<Status> {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let mut connected: Option<Instant> = None;
+
+            let stream = connect_easy_tls(&name, &token).await.context(Connect {
+                server: TWITCH_IRC_ADDRESS_TLS,
+            });
+
+            match stream {
+                Ok(stream) => {
+                    // the dispatcher caches the ready event of the first connection, so wait for
+                    // the next one on a fresh subscription
+                    let irc_ready = self.dispatcher.subscribe::<events::IrcReady>();
+
+                    let done = runner.run(stream);
+                    tokio::pin!(done);
+
+                    let status = tokio::select! {
+                        status = &mut done => status,
+                        result = self.join_channels(context.clone(), irc_ready) => {
+                            connected = Some(Instant::now());
+                            if let Err(err) = result {
+                                error!("Failed to join channels: {}", err);
+                            }
+                            done.await
+                        }
+                    };
+
+                    match status.context(RunRunner) {
+                        Ok(Status::Canceled) => {
+                            info!("Connection closed");
+                            return Ok(Status::Canceled);
+                        }
+                        Ok(status) => warn!("Connection lost: {:?}", status),
+                        Err(err) => error!("Connection lost: {}", err),
+                    }
+
+                    // the runner is used up. messages sent while reconnecting wait for the next one
+                    runner = self.renew();
+                }
+                Err(err) => error!("{}", err),
+            }
+
+            // start over if the connection was usable for a while
+            if connected.map_or(false, |t| t.elapsed() >= STABLE_CONNECTION) {
+                backoff = MIN_BACKOFF;
+            }
+
+            let count = self.reconnects.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!("Reconnecting in {:?} (reconnects: {})", backoff, count);
+
+            delay_for(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    }
+
+    /// Create the runner for the next connection and use its control from now on.
+    fn renew(&self) -> Runner {
+        let (runner, control) = Runner::new(self.dispatcher.clone(), RateLimit::default());
+        self.connection.replace(control);
+
+        runner
+    }
+
+    /// Wait until the connection is ready and join all enabled channels.
+    async fn join_channels(
+        &self,
+        context: Arc<BotContext>,
+        mut irc_ready: EventStream<Arc<messages::IrcReady<'static>>>,
+    ) -> Result<()> {
+        let ready = irc_ready.next().await.context(WaitForIrcReady)?;
+        info!(
+            "Connected to {} as {}",
+            twitchchat::TWITCH_IRC_ADDRESS_TLS,
+            ready.nickname
+        );
+
+        let channels = blocking(move || database::Channel::all_enabled(&context.conn()))
+            .await
+            .context(Blocking)?
+            .context(GetEnabledChannels)?;
+
+        info!("Joining twitch channels (count: {})", channels.len());
+        for channel in channels {
+            debug!("Joining channel (name: {})", &channel);
+            self.writer()
+                .join(&channel)
+                .await
+                .context(JoinChannel { channel })?;
+        }
+
+        Ok(())
+    }
+
+    async fn run(
+        &self,
+        context: Arc<BotContext>,
+        handlers: Arc<[Arc<dyn Twitch>]>,
+        event_handlers: Arc<[Arc<dyn TwitchEvents>]>,
+    ) -> Result<()> {
+        // subscribe to the events we're interested in
+        let mut privmsg = self.dispatcher.subscribe::<events::Privmsg>();
+        let mut join = self.dispatcher.subscribe::<events::Join>();
+        let mut user_state = self.dispatcher.subscribe::<events::UserState>();
+        let mut whisper = self.dispatcher.subscribe::<events::Whisper>();
+        let mut user_notice = self.dispatcher.subscribe::<events::UserNotice>();
+        let mut clear_chat = self.dispatcher.subscribe::<events::ClearChat>();
+        let mut room_state = self.dispatcher.subscribe::<events::RoomState>();
+        let mut notice = self.dispatcher.subscribe::<events::Notice>();
+
+        // every channel gets its own worker so messages are handled in order. whispers share one
+        // worker, a worker per user would never be removed
+        let mut workers: HashMap<String, UnboundedSender<Message<'static>>> = HashMap::new();
+
+        // and then our 'main loop'
+        loop {
+            tokio::select! {
+                Some(msg) = privmsg.next() => {
+                    trace!("Got chat message (provider: twitch, channel: {})", &msg.channel);
+                    let worker = workers
+                        .entry(msg.channel.to_string())
+                        .or_insert_with(|| self.spawn_worker(context.clone(), handlers.clone()));
+                    if worker.send(Message::TwitchPrivmsg(msg)).is_err() {
+                        error!("Chat message worker stopped");
+                    }
+                },
+                Some(msg) = whisper.next() => {
+                    trace!("Got whisper (provider: twitch, user: {})", &msg.name);
+                    let worker = workers
+                        .entry(String::from("whisper"))
+                        .or_insert_with(|| self.spawn_worker(context.clone(), handlers.clone()));
+                    let msg = Message::TwitchWhisper(WhisperMessage {
+                        whisper: msg,
+                        channel: format!("#{}", context.bot_name()),
+                        received_ts: Utc::now().timestamp_millis() as u64,
+                    });
+                    if worker.send(msg).is_err() {
+                        error!("Whisper worker stopped");
+                    }
+                },
+                Some(msg) = join.next() => {
+                    trace!("Got join message (provider: twitch, channel: {})", &msg.channel);
+                    if let Err(err) = self.handle_join(context.clone(), &msg).await {
+                        error!("Failed to handle join: {}", err);
+                    };
+                    trace!("Finished handling join messag (provider: twitch)");
+                },
+                Some(msg) = user_state.next() => {
+                    trace!("Got user state message (provider: twitch, channel: {})", &msg.channel);
+                    let broadcaster = msg.channel.trim_start_matches('#') == context.bot_name();
+                    self.queue.set_moderator(&msg.channel, broadcaster || msg.is_moderator());
+                },
+                Some(msg) = user_notice.next() => {
+                    trace!("Got user notice (provider: twitch, channel: {})", &msg.channel);
+                    dispatch_event(&event_handlers, "user notice", move |h| h.user_notice(msg.clone()));
+                },
+                Some(msg) = clear_chat.next() => {
+                    trace!("Got clear chat (provider: twitch, channel: {})", &msg.channel);
+                    dispatch_event(&event_handlers, "clear chat", move |h| h.clear_chat(msg.clone()));
+                },
+                Some(msg) = room_state.next() => {
+                    trace!("Got room state (provider: twitch, channel: {})", &msg.channel);
+                    dispatch_event(&event_handlers, "room state", move |h| h.room_state(msg.clone()));
+                },
+                Some(msg) = notice.next() => {
+                    trace!("Got notice (provider: twitch, channel: {})", &msg.channel);
+                    dispatch_event(&event_handlers, "notice", move |h| h.notice(msg.clone()));
+                },
+                else => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spawn a worker handling the messages of a single channel or user. Every message is handled
+    /// in its own task but the next message is only handled after the previous one is done.
+    fn spawn_worker(
+        &self,
+        context: Arc<BotContext>,
+        handlers: Arc<[Arc<dyn Twitch>]>,
+    ) -> UnboundedSender<Message<'static>> {
+        let (sender, mut receiver) = unbounded_channel::<Message<'static>>();
+        let bot = self.clone();
+
+        tokio::spawn(async move {
+            while let Some(msg) = receiver.recv().await {
+                let bot = bot.clone();
+                let context = context.clone();
+                let handlers = handlers.clone();
+
+                let task = tokio::spawn(async move {
+                    if let Err(err) = bot.handle_message(context, &handlers, msg).await {
+                        error!("Failed to handle message: {}", err);
+                    };
+                    trace!("Finished handling chat message (provider: twitch)");
+                });
+
+                if let Err(err) = task.await {
+                    error!("Handling message panicked: {}", err);
+                }
+            }
+        });
+
+        sender
+    }
+
+    async fn handle_message(
+        &self,
+        context: Arc<BotContext>,
+        handlers: &Arc<[Arc<dyn Twitch>]>,
+        msg: Message<'static>,
+    ) -> Result<()> {
+        // this variable name should not be changed.
+        // having no name or `_` as name just drops the Stopwatch instantly.
+        // and having no _ infront annoys the compiler
+        let _stopwatch = Stopwatch::new(|d| {
+            debug!("Handling message took: {}", d);
+        });
+
+        trace!("Got message");
+
+        if msg.name() == context.bot_name() {
+            // message must be sent by the bot -> ignore it
+            trace!("ignoring message since it was sent by the bot");
+            return Ok(());
+        }
+
+        // bump the user in database
+        let user = {
+            let user_id = msg
+                .twitch_id()
+                .context(GetUserID)?
+                .try_into()
+                .context(ConvertUserID)?;
+            let name = msg.name().to_owned();
+            let display_name = msg.display_name().context(GetDisplayName)?.to_string();
+            let now = Local::now();
+
+            let context = context.clone();
+            let user =
+                blocking(move || User::bump(&context.conn(), user_id, &name, &display_name, &now))
+                    .await
+                    .context(Blocking)?
+                    .context(BumpUser)?;
+
+            if user.banned(&now) {
+                trace!("User {} is banned. Ignoring message.", user.name);
+                return Ok(());
+            }
+
+            user
+        };
+
+        for handler in handlers.iter() {
+            trace!(
+                "Letting handler handle Message (handler: {})",
+                handler.name()
+            );
+            match handler.clone().handle(msg.clone(), &user).await {
+                Ok(_) => {}
+                Err(err) => error!(
+                    "Could not handle message (handler: {}): {:?}",
+                    handler.name(),
+                    err,
+                ),
+            };
+        }
+
+        Ok(())
+    }
+
+    async fn handle_join(&self, context: Arc<BotContext>, msg: &messages::Join<'_>) -> Result<()> {
+        if msg.channel == context.bot_name() {
+            // we've joined a channel
+            info!("Joined {}", msg.channel);
+
+            self.say(
+                &msg.channel,
+                &format!("Connected with version {}", context.version),
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn stop(&self) {
+        // get control
+        let connection = self.connection.clone();
+
+        // spawn thread to stop bot
+        tokio::spawn(async move { connection.stop() });
+    }
+
+    pub async fn join(&self, channel: &str) -> Result<()> {
+        self.writer()
+            .join(channel)
+            .await
+            .context(JoinChannel { channel })
+    }
+
+    pub async fn part(&self, channel: &str) -> Result<()> {
+        self.writer()
+            .part(channel)
+            .await
+            .context(PartChannel { channel })
+    }
+
+    /// Queue a message for `channel`. Messages longer than `MAX_TEXT_LENGTH` are split.
+    pub fn say(&self, channel: &str, text: &str) {
+        self.queue.push(channel, text)
+    }
+
+    /// Queue a whisper for `user`. Messages longer than `MAX_TEXT_LENGTH` are split.
+    pub fn whisper(&self, user: &str, text: &str) {
+        self.queue.whisper(user, text)
+    }
+
+    /// Send the result of a command or action. The result is whispered if `msg` is a whisper or
+    /// the result asks for it. Replies mention `user` if `mention` is set.
+    pub fn respond(&self, msg: &Message, user: &User, result: MessageResult, mention: bool) {
+        let whisper = msg.is_whisper() || matches!(result, MessageResult::Whisper(_));
+
+        if let Some(text) = result.into_text(user, mention) {
+            if whisper {
+                self.whisper(&user.name, &text)
+            } else {
+                self.say(msg.channel(), &text)
+            }
+        }
+    }
+
+    pub fn writer(&self) -> Writer {
+        self.connection.writer()
+    }
+
+    /// Get how often the bot reconnected since it was started.
+    pub fn reconnects(&self) -> usize {
+        self.reconnects.load(Ordering::Relaxed)
+    }
+}
+
+/// Let every event handler handle an event in a new task.
+fn dispatch_event<F, Fut>(handlers: &Arc<[Arc<dyn TwitchEvents>]>, kind: &'static str, f: F)
+where
+    F: Fn(Arc<dyn TwitchEvents>) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = anyhow::Result<()>> + Send + 'static,
+{
+    let handlers = handlers.clone();
+
+    tokio::spawn(async move {
+        for handler in handlers.iter() {
+            if let Err(err) = f(handler.clone()).await {
+                error!(
+                    "Could not handle {} (handler: {}): {:?}",
+                    kind,
+                    handler.name(),
+                    err
+                );
+            }
+        }
+    });
+}
@@ -0,0 +1,493 @@
+//! Outgoing message queue
+//!
+//! Every message sent by the bot goes through this queue. Messages are split into chunks Twitch
+//! accepts and sent per channel in order while respecting the rate limits.
+use super::Connection;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::{delay_for, timeout},
+};
+
+/// The maximum length of a message in characters.
+pub const MAX_MESSAGE_LENGTH: usize = 500;
+
+/// The maximum length of a text that is sent as a single message. Room is left for the suffix that
+/// is appended to repeated messages.
+pub const MAX_TEXT_LENGTH: usize = MAX_MESSAGE_LENGTH - 2;
+
+/// Whispers are sent as `/w` commands in this channel.
+const WHISPER_CHANNEL: &str = "#jtv";
+
+/// Appended to a message if it is identical to the previous one.
+const DEDUP_SUFFIX: &str = " \u{e0000}";
+
+/// Twitch rejects identical messages within this window.
+const DEDUP_WINDOW: Duration = Duration::from_secs(30);
+
+/// The window of the global rate limit.
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(30);
+
+/// Messages per window when the bot is a regular user.
+const USER_RATE_LIMIT: usize = 20;
+
+/// Messages per window when the bot is a moderator.
+const MODERATOR_RATE_LIMIT: usize = 100;
+
+/// Delay between two messages in the same channel when the bot is a regular user.
+const USER_DELAY: Duration = Duration::from_millis(1100);
+
+/// Delay between two messages in the same channel when the bot is a moderator.
+const MODERATOR_DELAY: Duration = Duration::from_millis(100);
+
+/// Whispers per second.
+const WHISPERS_PER_SECOND: usize = 3;
+
+/// Whispers per minute.
+const WHISPERS_PER_MINUTE: usize = 100;
+
+/// Whisper workers stop after they were idle this long.
+const WHISPER_WORKER_IDLE: Duration = Duration::from_secs(5 * 60);
+
+/// Users that can be whispered to for the first time per day.
+const WHISPER_RECIPIENTS_PER_DAY: usize = 40;
+
+const SECOND: Duration = Duration::from_secs(1);
+const MINUTE: Duration = Duration::from_secs(60);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Where the queue sends messages to.
+#[derive(Clone)]
+enum Output {
+    Twitch(Connection),
+    /// Print messages to stdout. Used by the console transport.
+    Stdout,
+}
+
+#[derive(Clone)]
+pub struct Queue {
+    output: Output,
+    channels: Arc<Mutex<HashMap<String, UnboundedSender<String>>>>,
+    moderator: Arc<Mutex<HashSet<String>>>,
+    limiter: Arc<RateLimiter>,
+    whisper_limiter: Arc<WhisperLimiter>,
+}
+
+impl Queue {
+    pub(super) fn new(connection: Connection) -> Self {
+        Self::with_output(Output::Twitch(connection))
+    }
+
+    /// Create a queue that prints all messages to stdout.
+    pub fn stdout() -> Self {
+        Self::with_output(Output::Stdout)
+    }
+
+    fn with_output(output: Output) -> Self {
+        Self {
+            output,
+            channels: Arc::new(Mutex::new(HashMap::new())),
+            moderator: Arc::new(Mutex::new(HashSet::new())),
+            limiter: Arc::new(RateLimiter::default()),
+            whisper_limiter: Arc::new(WhisperLimiter::default()),
+        }
+    }
+
+    /// Queue `text` for sending in `channel`. Long messages are split.
+    pub fn push(&self, channel: &str, text: &str) {
+        let channel = normalize_channel(channel);
+        self.enqueue(channel.clone(), channel, None, "", text)
+    }
+
+    /// Queue `text` for whispering to `user`. Long messages are split.
+    ///
+    /// Every recipient gets its own worker so that a recipient waiting for the daily limit does
+    /// not hold up whispers to others.
+    pub fn whisper(&self, user: &str, text: &str) {
+        let user = user.to_lowercase();
+        self.enqueue(
+            format!("{} {}", WHISPER_CHANNEL, user),
+            WHISPER_CHANNEL.to_owned(),
+            Some(user.clone()),
+            &format!("/w {} ", user),
+            text,
+        )
+    }
+
+    /// Split `text` and queue every chunk with `prefix` prepended. `key` selects the worker.
+    fn enqueue(
+        &self,
+        key: String,
+        channel: String,
+        recipient: Option<String>,
+        prefix: &str,
+        text: &str,
+    ) {
+        let mut channels = self.channels.lock().unwrap();
+
+        let sender = channels.entry(key.clone()).or_insert_with(|| {
+            trace!("Spawning queue worker ({})", key);
+
+            let (sender, receiver) = unbounded_channel();
+            tokio::spawn(
+                self.clone()
+                    .worker(key.clone(), channel, recipient, receiver),
+            );
+            sender
+        });
+
+        let max = MAX_TEXT_LENGTH - prefix.chars().count();
+
+        for chunk in split_message(text, max) {
+            if sender.send(format!("{}{}", prefix, chunk)).is_err() {
+                error!("Queue worker stopped ({})", key);
+            }
+        }
+    }
+
+    /// Set whether the bot is a moderator in `channel`.
+    pub fn set_moderator(&self, channel: &str, moderator: bool) {
+        let channel = normalize_channel(channel);
+        let mut channels = self.moderator.lock().unwrap();
+
+        if moderator {
+            channels.insert(channel);
+        } else {
+            channels.remove(&channel);
+        }
+    }
+
+    fn is_moderator(&self, channel: &str) -> bool {
+        self.moderator.lock().unwrap().contains(channel)
+    }
+
+    async fn worker(
+        self,
+        key: String,
+        channel: String,
+        recipient: Option<String>,
+        mut receiver: UnboundedReceiver<String>,
+    ) {
+        let connection = match &self.output {
+            Output::Twitch(connection) => connection.clone(),
+            Output::Stdout => {
+                // there are no rate limits on stdout
+                while let Some(text) = self.next(&key, &recipient, &mut receiver).await {
+                    println!("{}: {}", channel, text);
+                }
+                return;
+            }
+        };
+        let mut last: Option<(String, Instant)> = None;
+
+        while let Some(mut text) = self.next(&key, &recipient, &mut receiver).await {
+            let moderator = self.is_moderator(&channel);
+
+            if let Some((last_text, sent)) = &last {
+                let delay = if moderator {
+                    MODERATOR_DELAY
+                } else {
+                    USER_DELAY
+                };
+                let elapsed = sent.elapsed();
+                if elapsed < delay {
+                    delay_for(delay - elapsed).await;
+                }
+
+                // Twitch drops identical messages
+                if last_text == &text && sent.elapsed() < DEDUP_WINDOW {
+                    text.push_str(DEDUP_SUFFIX);
+                }
+            }
+
+            match &recipient {
+                // whispers have their own limits
+                Some(recipient) => self.whisper_limiter.acquire(recipient).await,
+                None => {
+                    self.limiter
+                        .acquire(
+                            &channel,
+                            if moderator {
+                                MODERATOR_RATE_LIMIT
+                            } else {
+                                USER_RATE_LIMIT
+                            },
+                        )
+                        .await
+                }
+            }
+
+            if let Err(err) = connection.writer().privmsg(&channel, &text).await {
+                error!("Could not send privmsg (channel: {}): {}", channel, err);
+            }
+
+            last = Some((text, Instant::now()));
+        }
+    }
+
+    /// Wait for the next message of a worker. Whisper workers stop and remove themselves once
+    /// they were idle for a while, so that every user ever whispered to does not keep a worker.
+    async fn next(
+        &self,
+        key: &str,
+        recipient: &Option<String>,
+        receiver: &mut UnboundedReceiver<String>,
+    ) -> Option<String> {
+        if recipient.is_none() {
+            return receiver.recv().await;
+        }
+
+        match timeout(WHISPER_WORKER_IDLE, receiver.recv()).await {
+            Ok(text) => text,
+            Err(_) => {
+                // `enqueue` sends while holding the lock, so nothing is lost after the removal
+                let mut channels = self.channels.lock().unwrap();
+
+                match receiver.try_recv() {
+                    Ok(text) => Some(text),
+                    Err(_) => {
+                        trace!("Stopping idle queue worker ({})", key);
+                        channels.remove(key);
+                        None
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct RateLimiter {
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl RateLimiter {
+    /// Wait until a message can be sent without exceeding `limit`.
+    async fn acquire(&self, channel: &str, limit: usize) {
+        loop {
+            let wait = {
+                let mut sent = self.sent.lock().unwrap();
+                let now = Instant::now();
+
+                while sent
+                    .front()
+                    .map_or(false, |t| now.duration_since(*t) >= RATE_LIMIT_WINDOW)
+                {
+                    sent.pop_front();
+                }
+
+                if sent.len() < limit {
+                    sent.push_back(now);
+                    return;
+                }
+
+                sent.front()
+                    .map(|t| RATE_LIMIT_WINDOW - now.duration_since(*t))
+                    .unwrap_or_default()
+            };
+
+            warn!(
+                "Rate limit reached, delaying message (channel: {}, limit: {}, wait: {:?})",
+                channel, limit, wait
+            );
+
+            delay_for(wait).await;
+        }
+    }
+}
+
+/// Limits whispers per second, per minute and new recipients per day.
+#[derive(Default)]
+struct WhisperLimiter {
+    state: Mutex<WhisperState>,
+}
+
+impl WhisperLimiter {
+    /// Wait until a whisper to `recipient` can be sent without exceeding a limit.
+    async fn acquire(&self, recipient: &str) {
+        loop {
+            let wait = match self
+                .state
+                .lock()
+                .unwrap()
+                .try_send(recipient, Instant::now())
+            {
+                Some(wait) => wait,
+                None => return,
+            };
+
+            warn!(
+                "Whisper limit reached, delaying whisper (recipient: {}, wait: {:?})",
+                recipient, wait
+            );
+
+            delay_for(wait).await;
+        }
+    }
+}
+
+#[derive(Default)]
+struct WhisperState {
+    /// Whispers sent in the last minute.
+    sent: VecDeque<Instant>,
+    /// When users were whispered to for the first time in the last day.
+    recipients: HashMap<String, Instant>,
+}
+
+impl WhisperState {
+    /// Record a whisper to `recipient` sent at `now`. Returns how long to wait instead if that
+    /// would exceed a limit.
+    fn try_send(&mut self, recipient: &str, now: Instant) -> Option<Duration> {
+        while self
+            .sent
+            .front()
+            .map_or(false, |t| now.duration_since(*t) >= MINUTE)
+        {
+            self.sent.pop_front();
+        }
+        self.recipients
+            .retain(|_, first| now.duration_since(*first) < DAY);
+
+        let mut waits = Vec::new();
+
+        let last_second: Vec<_> = self
+            .sent
+            .iter()
+            .filter(|t| now.duration_since(**t) < SECOND)
+            .collect();
+        if last_second.len() >= WHISPERS_PER_SECOND {
+            waits.push(SECOND - now.duration_since(*last_second[0]));
+        }
+
+        if self.sent.len() >= WHISPERS_PER_MINUTE {
+            waits.push(MINUTE - now.duration_since(self.sent[0]));
+        }
+
+        if !self.recipients.contains_key(recipient)
+            && self.recipients.len() >= WHISPER_RECIPIENTS_PER_DAY
+        {
+            if let Some(first) = self.recipients.values().min() {
+                waits.push(DAY - now.duration_since(*first));
+            }
+        }
+
+        if let Some(wait) = waits.into_iter().max() {
+            return Some(wait);
+        }
+
+        self.sent.push_back(now);
+        self.recipients.entry(recipient.to_owned()).or_insert(now);
+
+        None
+    }
+}
+
+fn normalize_channel(channel: &str) -> String {
+    format!("#{}", channel.trim_start_matches('#').to_lowercase())
+}
+
+/// Split `text` into chunks of at most `max` characters. If possible chunks end at whitespace.
+pub fn split_message(text: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text.trim();
+
+    while rest.chars().count() > max {
+        // byte index of the char boundary after `max` chars
+        let end = rest
+            .char_indices()
+            .nth(max)
+            .map(|(i, _)| i)
+            .unwrap_or_else(|| rest.len());
+
+        let split = if rest[end..].starts_with(char::is_whitespace) {
+            end
+        } else {
+            rest[..end]
+                .rfind(char::is_whitespace)
+                .filter(|&i| i > 0)
+                .unwrap_or(end)
+        };
+
+        chunks.push(rest[..split].trim_end().to_owned());
+        rest = rest[split..].trim_start();
+    }
+
+    if !rest.is_empty() {
+        chunks.push(rest.to_owned());
+    }
+
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("short", 10), vec!["short"]);
+        assert_eq!(split_message("", 10), Vec::<String>::new());
+
+        assert_eq!(
+            split_message("some words that are long", 10),
+            vec!["some words", "that are", "long"]
+        );
+
+        assert_eq!(
+            split_message("abcdefghijklmnop", 5),
+            vec!["abcde", "fghij", "klmno", "p"]
+        );
+
+        // never split inside a char
+        assert_eq!(split_message("äöüäöüäöü", 4), vec!["äöüä", "öüäö", "ü"]);
+    }
+
+    #[test]
+    fn test_whisper_limits() {
+        let mut state = WhisperState::default();
+        let now = Instant::now();
+
+        for _ in 0..WHISPERS_PER_SECOND {
+            assert_eq!(state.try_send("bob", now), None);
+        }
+        assert_eq!(state.try_send("bob", now), Some(SECOND));
+
+        // the minute limit, a whisper every 400ms never reaches the second limit
+        let mut state = WhisperState::default();
+        let step = Duration::from_millis(400);
+        for i in 0..WHISPERS_PER_MINUTE {
+            assert_eq!(state.try_send("bob", now + step * i as u32), None);
+        }
+        let t = now + Duration::from_secs(40);
+        assert_eq!(state.try_send("bob", t), Some(Duration::from_secs(20)));
+
+        // the new recipient limit
+        let mut state = WhisperState::default();
+        for i in 0..WHISPER_RECIPIENTS_PER_DAY {
+            let t = now + MINUTE * i as u32;
+            assert_eq!(state.try_send(&format!("user{}", i), t), None);
+        }
+        let t = now + MINUTE * WHISPER_RECIPIENTS_PER_DAY as u32;
+        assert_eq!(state.try_send("alice", t), Some(DAY - (t - now)));
+        assert_eq!(state.try_send("user0", t), None);
+        assert_eq!(state.try_send("alice", now + DAY), None);
+    }
+
+    #[test]
+    fn test_max_text_length() {
+        assert_eq!(
+            MAX_TEXT_LENGTH + DEDUP_SUFFIX.chars().count(),
+            MAX_MESSAGE_LENGTH
+        );
+    }
+
+    #[test]
+    fn test_normalize_channel() {
+        assert_eq!(normalize_channel("#Chronophylos"), "#chronophylos");
+        assert_eq!(normalize_channel("chronophylos"), "#chronophylos");
+    }
+}
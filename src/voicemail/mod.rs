@@ -2,5 +2,6 @@ mod parsed;
 mod parser;
 mod scheduler;
 
-pub use parsed::Voicemail;
+pub use parsed::{Day, Schedule, Voicemail};
+pub use parser::parse_duration;
 pub use scheduler::Scheduler;
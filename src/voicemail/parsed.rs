@@ -1,4 +1,5 @@
 use super::parser::parse_voicemail;
+use crate::timezone::Zone;
 use chrono::prelude::*;
 use nom::{error::ErrorKind, Err};
 use snafu::Snafu;
@@ -15,7 +16,99 @@ type Result<T> = std::result::Result<T, Error>;
 pub struct Voicemail {
     pub recipients: Vec<String>,
     pub message: String,
-    pub schedule: Option<NaiveDateTime>,
+    pub schedule: Option<Schedule>,
+}
+
+/// When a voicemail should be sent. Use `resolve` to get the point in time.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Schedule {
+    /// A point in time in UTC.
+    At(NaiveDateTime),
+    /// A duration from now.
+    In(chrono::Duration),
+    /// A wall-clock time. Without a zone the zone of the sender is used.
+    Local {
+        day: Day,
+        time: Option<NaiveTime>,
+        zone: Option<Zone>,
+    },
+}
+
+/// The day of a wall-clock schedule.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Day {
+    /// The next day the time is still ahead.
+    Next,
+    Tomorrow,
+    /// The next weekday the time is still ahead.
+    Weekday(Weekday),
+    /// The next time the day and month come.
+    DayMonth(u32, u32),
+    Date(NaiveDate),
+}
+
+impl Schedule {
+    /// Get the point in time in UTC. `zone` is used if the schedule has no zone of its own.
+    /// Returns `None` if the time does not exist in the zone.
+    pub fn resolve(&self, now: NaiveDateTime, zone: &Zone) -> Option<NaiveDateTime> {
+        let (day, time, zone) = match self {
+            Self::At(at) => return Some(*at),
+            Self::In(duration) => return now.checked_add_signed(*duration),
+            Self::Local {
+                day,
+                time,
+                zone: own,
+            } => (*day, *time, own.as_ref().unwrap_or(zone)),
+        };
+
+        let now = zone.to_local(&now);
+        let midnight = time.unwrap_or_else(|| NaiveTime::from_hms(0, 0, 0));
+
+        let local = match day {
+            Day::Next => next_time(now, midnight),
+            Day::Tomorrow => (now.date() + chrono::Duration::days(1)).and_time(midnight),
+            Day::Weekday(weekday) => next_weekday(now, weekday, midnight),
+            Day::DayMonth(day, month) => next_date(now, day, month, midnight)?,
+            Day::Date(date) => date.and_time(midnight),
+        };
+
+        zone.to_utc(&local)
+    }
+
+    /// Get the zone written with the schedule.
+    pub fn zone(&self) -> Option<&Zone> {
+        match self {
+            Self::Local { zone, .. } => zone.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// Get the next date and time after `now` with the time `time`.
+fn next_time(now: NaiveDateTime, time: NaiveTime) -> NaiveDateTime {
+    let date_time = now.date().and_time(time);
+
+    if date_time > now {
+        date_time
+    } else {
+        date_time + chrono::Duration::days(1)
+    }
+}
+
+/// Get the next date and time after `now` on `weekday` at `time`.
+fn next_weekday(now: NaiveDateTime, weekday: Weekday, time: NaiveTime) -> NaiveDateTime {
+    (0..=7)
+        .map(|days| (now.date() + chrono::Duration::days(days)).and_time(time))
+        .find(|date_time| date_time.weekday() == weekday && *date_time > now)
+        .unwrap_or(now)
+}
+
+/// Get the next date and time after `now` on `day`.`month`. at `time`.
+fn next_date(now: NaiveDateTime, day: u32, month: u32, time: NaiveTime) -> Option<NaiveDateTime> {
+    (now.year()..=now.year() + 4)
+        .filter_map(|year| NaiveDate::from_ymd_opt(year, month, day))
+        .map(|date| date.and_time(time))
+        .find(|date_time| *date_time > now)
 }
 
 impl FromStr for Voicemail {
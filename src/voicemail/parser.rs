@@ -10,13 +10,31 @@
 //! reci-sep-comma = [SP] "," SP
 //!
 //! message = *ALPHA *(SP *ALPHA)
-//! schedule = SP absolute-schedule / relative-schedule
+//! schedule = SP absolute-schedule / relative-schedule / tomorrow-schedule
 //!
 //! absolute-schedule = "on" / "at" SP absolute-schedule-spec
+//! absolute-schedule =/ "at" SP clock
 //! absolute-schedule-spec = rfc2822 ; see https://tools.ietf.org/html/rfc2822#section-3.3
-//! absolute-schedule-spec =/ rfc3339.date ; see https://tools.ietf.org/html/rfc3339#appendix-A
-//! absolute-schedule-spec =/ rfc3339.time
-//! absolute-schedule-spec =/ rfc3339.iso-date-time
+//! absolute-schedule-spec =/ rfc3339.iso-date-time ; see https://tools.ietf.org/html/rfc3339#appendix-A
+//! absolute-schedule-spec =/ rfc3339.date
+//! absolute-schedule-spec =/ german-date [day-time]
+//! absolute-schedule-spec =/ weekday [day-time]
+//! absolute-schedule-spec =/ time
+//!
+//! tomorrow-schedule = "tomorrow" day-time
+//!
+//! Everything but rfc2822 and relative schedules may be followed by SP zone, where zone is an
+//! uppercase abbreviation like "CET" or a name from the tz database like "Europe/Berlin". Without
+//! a zone the timezone of the sender is used.
+//!
+//! german-date = 1*2DIGIT "." 1*2DIGIT "." [4DIGIT]
+//! weekday = "monday" / "mon" / "montag" / ... ; english and german names
+//! day-time = SP ("at" SP clock / time)
+//! clock = "noon" / "midnight" / time / 1*2DIGIT
+//! time = 1*2DIGIT ":" 2DIGIT [":" 2DIGIT]
+//!
+//! Times without a date are the next time the clock shows that time. Dates without a year are the
+//! next time that day comes.
 //!
 //! relative-schedule = "in" SP relative-schedule-spec *(SP relative-schedule-spec)
 //! relative-schedule-spec = amount [SP] time-unit
@@ -50,19 +68,49 @@
 //                                        |
 //                     left over characters
 
-use super::Voicemail;
+use super::{Day, Schedule, Voicemail};
+use crate::timezone::Zone;
 use chrono::prelude::*;
 use nom::{
     branch::alt,
-    bytes::complete::{tag, tag_no_case, take_while, take_while_m_n},
-    character::complete::digit1,
-    combinator::{map, map_res, opt},
+    bytes::complete::{tag, tag_no_case, take_while, take_while1, take_while_m_n},
+    character::complete::{alpha1, digit1},
+    combinator::{map, map_opt, map_res, not, opt, recognize},
+    error::ErrorKind,
     multi::{fold_many0, separated_list},
-    sequence::pair,
+    sequence::{pair, preceded, terminated, tuple},
     IResult,
 };
 use std::time::Duration;
 
+/// English and german names of weekdays.
+const WEEKDAYS: &[(&str, Weekday)] = &[
+    ("monday", Weekday::Mon),
+    ("montag", Weekday::Mon),
+    ("mon", Weekday::Mon),
+    ("tuesday", Weekday::Tue),
+    ("dienstag", Weekday::Tue),
+    ("tues", Weekday::Tue),
+    ("tue", Weekday::Tue),
+    ("wednesday", Weekday::Wed),
+    ("mittwoch", Weekday::Wed),
+    ("wed", Weekday::Wed),
+    ("thursday", Weekday::Thu),
+    ("donnerstag", Weekday::Thu),
+    ("thurs", Weekday::Thu),
+    ("thu", Weekday::Thu),
+    ("friday", Weekday::Fri),
+    ("freitag", Weekday::Fri),
+    ("fri", Weekday::Fri),
+    ("saturday", Weekday::Sat),
+    ("samstag", Weekday::Sat),
+    ("sonnabend", Weekday::Sat),
+    ("sat", Weekday::Sat),
+    ("sunday", Weekday::Sun),
+    ("sonntag", Weekday::Sun),
+    ("sun", Weekday::Sun),
+];
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 enum Units {
     Second,
@@ -78,15 +126,8 @@ enum Units {
     Century,
 }
 
-#[cfg(not(test))]
-fn now() -> NaiveDateTime {
-    Utc::now().naive_utc()
-}
-
-#[cfg(test)]
-fn now() -> NaiveDateTime {
-    NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0)
-}
+/// A day and an optional time, the part of a schedule before the zone.
+type DayTime = (Day, Option<NaiveTime>);
 
 /// century = "century" ["s"]
 fn parse_century<'a>(i: &'a str) -> IResult<&'a str, Units> {
@@ -263,6 +304,11 @@ fn parse_relative_schedule<'a>(i: &'a str) -> IResult<&'a str, chrono::Duration>
     let (i, _) = tag_no_case("in")(i)?;
     let (i, _) = take_space(i)?;
 
+    parse_relative_schedule_specs(i)
+}
+
+/// relative-schedule-spec *([SP] relative-schedule-spec)
+fn parse_relative_schedule_specs<'a>(i: &'a str) -> IResult<&'a str, chrono::Duration> {
     let (i, dur) = parse_relative_schedule_spec(i)?;
     fold_many0(
         |i| {
@@ -292,37 +338,153 @@ where
     map_res(take_while_m_n(2, 2, is_number), str::parse)(i)
 }
 
-#[allow(dead_code)]
-fn parse_rfc2822<'a>(_i: &'a str) -> IResult<&'a str, DateTime<Utc>> {
-    unimplemented!()
+fn take_space1<'a>(i: &'a str) -> IResult<&'a str, &'a str> {
+    take_while1(char::is_whitespace)(i)
 }
 
-fn parse_rfc3339_date<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
-    let (i, year) = num_4::<i32>(i)?;
-    let (i, _) = tag("-")(i)?;
-    let (i, month) = num_2::<u32>(i)?;
-    let (i, _) = tag("-")(i)?;
-    let (i, day) = num_2::<u32>(i)?;
+/// Succeeds if `i` does not continue the current word.
+fn word_end<'a>(i: &'a str) -> IResult<&'a str, ()> {
+    not(take_while_m_n(1, 1, char::is_alphanumeric))(i)
+}
 
-    Ok((i, NaiveDate::from_ymd(year, month, day).and_hms(0, 0, 0)))
+fn is_zone_name(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "/_+-".contains(c)
 }
 
-fn parse_rfc3339_time<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
-    let (i, hour) = num_2::<u32>(i)?;
-    let (i, _) = tag(":")(i)?;
-    let (i, minute) = num_2::<u32>(i)?;
-    let (i, maybe_second) = opt(pair(tag(":"), num_2::<u32>))(i)?;
-    let (_, second) = maybe_second.unwrap_or(("", 0));
+/// zone = SP (1*UPPER / tz-name)
+///
+/// Only names with a `/` or in uppercase are accepted so that the message is not mistaken for a
+/// zone.
+fn parse_zone<'a>(i: &'a str) -> IResult<&'a str, Zone> {
+    preceded(
+        take_space1,
+        map_opt(take_while1(is_zone_name), |name: &str| {
+            if name.contains('/') || name.chars().all(|c| c.is_ascii_uppercase()) {
+                Zone::parse(name)
+            } else {
+                None
+            }
+        }),
+    )(i)
+}
+
+/// rfc2822 = [day-of-week ","] SP date SP time SP zone
+fn parse_rfc2822<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
+    map_res(
+        recognize(tuple((
+            opt(pair(alpha1, tag(","))),
+            take_space,
+            take_while_m_n(1, 2, is_number),
+            take_space1,
+            alpha1,
+            take_space1,
+            take_while_m_n(4, 4, is_number),
+            take_space1,
+            parse_time,
+            take_space1,
+            alt((
+                recognize(pair(
+                    alt((tag("+"), tag("-"))),
+                    take_while_m_n(4, 4, is_number),
+                )),
+                alpha1,
+            )),
+        ))),
+        |date_time: &str| DateTime::parse_from_rfc2822(date_time).map(|d| d.naive_utc()),
+    )(i)
+}
+
+fn parse_rfc3339_date<'a>(i: &'a str) -> IResult<&'a str, NaiveDate> {
+    map_opt(
+        tuple((
+            num_4::<i32>,
+            preceded(tag("-"), num_2::<u32>),
+            preceded(tag("-"), num_2::<u32>),
+        )),
+        |(year, month, day)| NaiveDate::from_ymd_opt(year, month, day),
+    )(i)
+}
+
+/// time = 1*2DIGIT ":" 2DIGIT [":" 2DIGIT]
+fn parse_time<'a>(i: &'a str) -> IResult<&'a str, NaiveTime> {
+    map_opt(
+        tuple((
+            map_res(take_while_m_n(1, 2, is_number), str::parse::<u32>),
+            preceded(tag(":"), num_2::<u32>),
+            opt(preceded(tag(":"), num_2::<u32>)),
+        )),
+        |(hour, minute, second)| NaiveTime::from_hms_opt(hour, minute, second.unwrap_or(0)),
+    )(i)
+}
+
+/// clock = "noon" / "midnight" / time / 1*2DIGIT
+fn parse_clock<'a>(i: &'a str) -> IResult<&'a str, NaiveTime> {
+    alt((
+        map(terminated(tag_no_case("noon"), word_end), |_| {
+            NaiveTime::from_hms(12, 0, 0)
+        }),
+        map(terminated(tag_no_case("midnight"), word_end), |_| {
+            NaiveTime::from_hms(0, 0, 0)
+        }),
+        parse_time,
+        map_opt(
+            terminated(
+                map_res(take_while_m_n(1, 2, is_number), str::parse::<u32>),
+                word_end,
+            ),
+            |hour| NaiveTime::from_hms_opt(hour, 0, 0),
+        ),
+    ))(i)
+}
+
+/// day-time = SP ("at" SP clock / time)
+fn parse_day_time<'a>(i: &'a str) -> IResult<&'a str, NaiveTime> {
+    let (i, _) = take_space1(i)?;
+
+    alt((
+        preceded(pair(tag_no_case("at"), take_space1), parse_clock),
+        parse_time,
+    ))(i)
+}
+
+/// german-date = 1*2DIGIT "." 1*2DIGIT "." [4DIGIT]
+fn parse_german_date<'a>(i: &'a str) -> IResult<&'a str, DayTime> {
+    let (i, day) = map_res(take_while_m_n(1, 2, is_number), str::parse::<u32>)(i)?;
+    let (i, _) = tag(".")(i)?;
+    let (i, month) = map_res(take_while_m_n(1, 2, is_number), str::parse::<u32>)(i)?;
+    let (i, _) = tag(".")(i)?;
+    let (i, year) = opt(num_4::<i32>)(i)?;
+    let (i, time) = opt(parse_day_time)(i)?;
+
+    let day = match year {
+        Some(year) => NaiveDate::from_ymd_opt(year, month, day).map(Day::Date),
+        // 2000 is a leap year so every day that can come is accepted
+        None => NaiveDate::from_ymd_opt(2000, month, day).map(|_| Day::DayMonth(day, month)),
+    };
+
+    match day {
+        Some(day) => Ok((i, (day, time))),
+        None => Err(nom::Err::Error((i, ErrorKind::MapOpt))),
+    }
+}
+
+/// weekday [day-time]
+fn parse_weekday<'a>(i: &'a str) -> IResult<&'a str, DayTime> {
+    let (i, weekday) = WEEKDAYS
+        .iter()
+        .find_map(|(name, weekday)| {
+            terminated(tag_no_case(*name), word_end)(i)
+                .ok()
+                .map(|(i, _)| (i, *weekday))
+        })
+        .ok_or_else(|| nom::Err::Error((i, ErrorKind::Tag)))?;
+    let (i, time) = opt(parse_day_time)(i)?;
 
-    Ok((i, Utc::today().naive_utc().and_hms(hour, minute, second)))
+    Ok((i, (Day::Weekday(weekday), time)))
 }
 
-fn parse_rfc3339_date_time<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
-    let (i, year) = num_4::<i32>(i)?;
-    let (i, _) = tag("-")(i)?;
-    let (i, month) = num_2::<u32>(i)?;
-    let (i, _) = tag("-")(i)?;
-    let (i, day) = num_2::<u32>(i)?;
+fn parse_rfc3339_date_time<'a>(i: &'a str) -> IResult<&'a str, DayTime> {
+    let (i, date) = parse_rfc3339_date(i)?;
 
     let (i, _) = alt((tag("T"), take_space))(i)?;
 
@@ -332,43 +494,75 @@ fn parse_rfc3339_date_time<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
     let (i, maybe_second) = opt(pair(tag(":"), num_2::<u32>))(i)?;
     let (_, second) = maybe_second.unwrap_or(("", 0));
 
-    Ok((
-        i,
-        NaiveDate::from_ymd(year, month, day).and_hms(hour, minute, second),
-    ))
+    match NaiveTime::from_hms_opt(hour, minute, second) {
+        Some(time) => Ok((i, (Day::Date(date), Some(time)))),
+        None => Err(nom::Err::Error((i, ErrorKind::MapOpt))),
+    }
 }
 
-/// absolute-schedule-spec = rfc2822 ; see https://tools.ietf.org/html/rfc2822#section-3.3
-/// absolute-schedule-spec =/ rfc3339.date ; see https://tools.ietf.org/html/rfc3339#appendix-A
-/// absolute-schedule-spec =/ rfc3339.time
-/// absolute-schedule-spec =/ rfc3339.iso-date-time
-fn parse_absoulute_schedule_spec<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
-    let (i, date) = alt((
-        //parse_rfc2822, // TODO: implement
-        parse_rfc3339_date_time,
-        parse_rfc3339_date,
-        parse_rfc3339_time,
-    ))(i)?;
+/// Add the optional zone to a wall-clock schedule.
+fn local_schedule<'a>(
+    parser: impl Fn(&'a str) -> IResult<&'a str, DayTime>,
+) -> impl Fn(&'a str) -> IResult<&'a str, Schedule> {
+    map(pair(parser, opt(parse_zone)), |((day, time), zone)| {
+        Schedule::Local { day, time, zone }
+    })
+}
 
-    Ok((i, date))
+/// absolute-schedule-spec = rfc2822 ; see https://tools.ietf.org/html/rfc2822#section-3.3
+/// absolute-schedule-spec =/ rfc3339.iso-date-time ; see https://tools.ietf.org/html/rfc3339#appendix-A
+/// absolute-schedule-spec =/ rfc3339.date
+/// absolute-schedule-spec =/ german-date [day-time]
+/// absolute-schedule-spec =/ weekday [day-time]
+/// absolute-schedule-spec =/ time
+fn parse_absoulute_schedule_spec<'a>(i: &'a str) -> IResult<&'a str, Schedule> {
+    alt((
+        map(parse_rfc2822, Schedule::At),
+        local_schedule(alt((
+            parse_rfc3339_date_time,
+            map(parse_rfc3339_date, |date| (Day::Date(date), None)),
+            parse_german_date,
+            parse_weekday,
+            map(parse_time, |time| (Day::Next, Some(time))),
+        ))),
+    ))(i)
 }
 
 /// absolute-schedule = "on" / "at" SP absolute-schedule-spec
-fn parse_absoulute_schedule<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
-    let (i, _) = alt((tag_no_case("on"), tag_no_case("at")))(i)?;
-    let (i, _) = take_space(i)?;
+/// absolute-schedule =/ "at" SP clock
+fn parse_absoulute_schedule<'a>(i: &'a str) -> IResult<&'a str, Schedule> {
+    alt((
+        |i| {
+            let (i, _) = alt((tag_no_case("on"), tag_no_case("at")))(i)?;
+            let (i, _) = take_space(i)?;
 
-    parse_absoulute_schedule_spec(i)
+            parse_absoulute_schedule_spec(i)
+        },
+        local_schedule(map(
+            preceded(pair(tag_no_case("at"), take_space), parse_clock),
+            |time| (Day::Next, Some(time)),
+        )),
+    ))(i)
 }
 
-/// schedule = SP absolute-schedule / relative-schedule
-fn parse_schedule<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
+/// tomorrow-schedule = "tomorrow" day-time
+///
+/// The time is required, a bare `tomorrow` is part of the message.
+fn parse_tomorrow_schedule<'a>(i: &'a str) -> IResult<&'a str, Schedule> {
+    local_schedule(|i| {
+        let (i, _) = terminated(tag_no_case("tomorrow"), word_end)(i)?;
+        let (i, time) = parse_day_time(i)?;
+
+        Ok((i, (Day::Tomorrow, Some(time))))
+    })(i)
+}
+
+/// schedule = SP absolute-schedule / relative-schedule / tomorrow-schedule
+fn parse_schedule<'a>(i: &'a str) -> IResult<&'a str, Schedule> {
     alt((
         parse_absoulute_schedule,
-        map(parse_relative_schedule, |d: chrono::Duration| {
-            println!("{:?}", d);
-            now() + d
-        }),
+        map(parse_relative_schedule, Schedule::In),
+        parse_tomorrow_schedule,
     ))(i)
 }
 
@@ -416,7 +610,7 @@ fn parse_recipients<'a>(i: &'a str) -> IResult<&'a str, Vec<&'a str>> {
     separated_list(parse_recipient_sep, parse_recipient_name)(i)
 }
 
-fn parse_schedule_with_space<'a>(i: &'a str) -> IResult<&'a str, NaiveDateTime> {
+fn parse_schedule_with_space<'a>(i: &'a str) -> IResult<&'a str, Schedule> {
     let (i, _) = take_space(i)?;
 
     parse_schedule(i)
@@ -438,28 +632,202 @@ pub fn parse_voicemail<'a>(i: &'a str) -> IResult<&'a str, Voicemail> {
     ))
 }
 
+/// Parse a duration like `1h` or `2 days 12h`. The whole input has to be a duration.
+pub fn parse_duration(i: &str) -> Option<chrono::Duration> {
+    match parse_relative_schedule_specs(i.trim()) {
+        Ok(("", dur)) => Some(dur),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn now() -> NaiveDateTime {
+        NaiveDate::from_ymd(2000, 1, 1).and_hms(0, 0, 0)
+    }
+
+    /// Parse and resolve a schedule in UTC.
+    fn schedule(i: &str) -> IResult<&str, NaiveDateTime> {
+        map_opt(parse_schedule, |s| s.resolve(now(), &Zone::utc()))(i)
+    }
+
     #[test]
     fn test_parse_schedule() {
         assert_eq!(
-            parse_schedule("on 2020-10-10"),
+            schedule("on 2020-10-10"),
             Ok(("", NaiveDate::from_ymd(2020, 10, 10).and_hms(0, 0, 0)))
         );
 
         assert_eq!(
-            parse_schedule("on 2020-10-10 12:34:56"),
+            schedule("on 2020-10-10 12:34:56"),
             Ok(("", NaiveDate::from_ymd(2020, 10, 10).and_hms(12, 34, 56)))
         );
 
         assert_eq!(
-            parse_schedule("in 2 days 2 week 4 months 1 hour 3 decades"),
+            schedule("in 2 days 2 week 4 months 1 hour 3 decades"),
             Ok(("", NaiveDate::from_ymd(2030, 5, 17).and_hms(23, 0, 0)))
         )
     }
 
+    #[test]
+    fn test_parse_schedule_clock() {
+        assert_eq!(
+            schedule("at noon"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(12, 0, 0)))
+        );
+
+        // midnight has passed already
+        assert_eq!(
+            schedule("at midnight"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 2).and_hms(0, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("at 9 wake up"),
+            Ok((" wake up", NaiveDate::from_ymd(2000, 1, 1).and_hms(9, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("at 18:30"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(18, 30, 0)))
+        );
+
+        assert!(schedule("at 25").is_err());
+        assert!(schedule("at 9am").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_tomorrow() {
+        assert_eq!(
+            schedule("tomorrow at 9"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 2).and_hms(9, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("tomorrow 13:37"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 2).and_hms(13, 37, 0)))
+        );
+
+        assert!(schedule("tomorrow").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_weekday() {
+        assert_eq!(
+            schedule("on friday 18:00"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 7).and_hms(18, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("on Freitag at noon"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 7).and_hms(12, 0, 0)))
+        );
+
+        // now is a saturday
+        assert_eq!(
+            schedule("on sat"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 8).and_hms(0, 0, 0)))
+        );
+        assert_eq!(
+            schedule("on saturday at 12"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(12, 0, 0)))
+        );
+
+        assert!(schedule("on fries").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_german_date() {
+        assert_eq!(
+            schedule("on 24.12."),
+            Ok(("", NaiveDate::from_ymd(2000, 12, 24).and_hms(0, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("on 24.12.2001 18:00"),
+            Ok(("", NaiveDate::from_ymd(2001, 12, 24).and_hms(18, 0, 0)))
+        );
+
+        // the first of january has passed already
+        assert_eq!(
+            schedule("on 1.1."),
+            Ok(("", NaiveDate::from_ymd(2001, 1, 1).and_hms(0, 0, 0)))
+        );
+
+        assert!(schedule("on 31.2.").is_err());
+    }
+
+    #[test]
+    fn test_parse_schedule_rfc2822() {
+        assert_eq!(
+            schedule("on Sat, 01 Jan 2000 12:00:00 +0100"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(11, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("at 2 Jan 2000 12:00 GMT hi"),
+            Ok((" hi", NaiveDate::from_ymd(2000, 1, 2).and_hms(12, 0, 0)))
+        );
+    }
+
+    #[test]
+    fn test_parse_schedule_zone() {
+        assert_eq!(
+            schedule("at 18:00 CET"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(17, 0, 0)))
+        );
+
+        // it is still the 31st of december in new york
+        assert_eq!(
+            schedule("at 20:00 America/New_York"),
+            Ok(("", NaiveDate::from_ymd(2000, 1, 1).and_hms(1, 0, 0)))
+        );
+
+        assert_eq!(
+            schedule("on 24.12. Europe/Berlin merry christmas"),
+            Ok((
+                " merry christmas",
+                NaiveDate::from_ymd(2000, 12, 23).and_hms(23, 0, 0)
+            ))
+        );
+
+        // lowercase words are part of the message
+        assert_eq!(
+            schedule("at noon cet"),
+            Ok((" cet", NaiveDate::from_ymd(2000, 1, 1).and_hms(12, 0, 0)))
+        );
+
+        // the time is resolved in the given zone
+        assert_eq!(
+            parse_schedule("at 9 UTC")
+                .unwrap()
+                .1
+                .resolve(now(), &Zone::parse("Europe/Berlin").unwrap()),
+            Some(NaiveDate::from_ymd(2000, 1, 1).and_hms(9, 0, 0))
+        );
+        assert_eq!(
+            parse_schedule("at 9")
+                .unwrap()
+                .1
+                .resolve(now(), &Zone::parse("Europe/Berlin").unwrap()),
+            Some(NaiveDate::from_ymd(2000, 1, 1).and_hms(8, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("1h"), Some(chrono::Duration::hours(1)));
+        assert_eq!(parse_duration("2d12h"), Some(chrono::Duration::hours(60)));
+        assert_eq!(
+            parse_duration("1 week 30 min"),
+            Some(chrono::Duration::minutes(10_110))
+        );
+        assert_eq!(parse_duration("permanent"), None);
+        assert_eq!(parse_duration("1h spam"), None);
+    }
+
     #[test]
     fn test_parse_voicemail() {
         assert_eq!(
@@ -481,7 +849,11 @@ mod tests {
                 Voicemail {
                     recipients: vec![String::from("coroner")],
                     message: String::from("does corona still exist?"),
-                    schedule: Some(NaiveDate::from_ymd(2020, 10, 24).and_hms(0, 0, 0))
+                    schedule: Some(Schedule::Local {
+                        day: Day::Date(NaiveDate::from_ymd(2020, 10, 24)),
+                        time: None,
+                        zone: None,
+                    })
                 }
             ))
         );
@@ -493,9 +865,38 @@ mod tests {
                 Voicemail {
                     recipients: vec![String::from("nizzlenils"), String::from("nizzlenico")],
                     message: String::from("Pepeja"),
-                    schedule: Some(NaiveDate::from_ymd(2000, 1, 15).and_hms(0, 0, 0)),
+                    schedule: Some(Schedule::In(chrono::Duration::weeks(2))),
                 }
             ))
-        )
+        );
+
+        assert_eq!(
+            parse_voicemail("chronophylos tomorrow at 9 wake up"),
+            Ok((
+                "",
+                Voicemail {
+                    recipients: vec![String::from("chronophylos")],
+                    message: String::from("wake up"),
+                    schedule: Some(Schedule::Local {
+                        day: Day::Tomorrow,
+                        time: Some(NaiveTime::from_hms(9, 0, 0)),
+                        zone: None,
+                    }),
+                }
+            ))
+        );
+
+        // a bare `tomorrow` is not a schedule
+        assert_eq!(
+            parse_voicemail("chronophylos tomorrow is the day"),
+            Ok((
+                "",
+                Voicemail {
+                    recipients: vec![String::from("chronophylos")],
+                    message: String::from("tomorrow is the day"),
+                    schedule: None,
+                }
+            ))
+        );
     }
 }
@@ -1,19 +1,25 @@
 use crate::{
     context::BotContext,
-    database::{self, Channel, User, Voicemail},
+    database::{self, Channel, User, UserSettings, Voicemail},
+    helpers::{blocking, truncate_duration},
 };
 use chrono::prelude::*;
-use futures_delay_queue::{delay_queue, DelayQueue, Receiver};
+use futures_delay_queue::{delay_queue, DelayHandle, DelayQueue, Receiver};
+use humantime::format_duration;
 use snafu::{OptionExt, ResultExt, Snafu};
-use std::sync::Arc;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
 
 #[derive(Debug, Snafu)]
 pub enum Error {
     #[snafu(display("Scheduled is not set"))]
     GetScheduled,
 
-    #[snafu(display("Converting to std duration: {}", source))]
-    ConvertToStdDuration { source: time::OutOfRangeError },
+    #[snafu(display("Getting database connection: {}", source))]
+    GetConnection { source: r2d2::Error },
 
     #[snafu(display("Getting voicemail: {}", source))]
     GetVoicemail { source: database::voicemail::Error },
@@ -36,91 +42,274 @@ pub enum Error {
     #[snafu(display("User not found (id: {})", id))]
     UserNotFound { id: i32 },
 
+    #[snafu(display("Getting settings: {}", source))]
+    GetSettings { source: database::settings::Error },
+
     #[snafu(display("Disabling voicemail: {}", source))]
     DisableVoicemail { source: database::voicemail::Error },
 
-    #[snafu(display("Sending privmsg (channel: {}): {}", channel, source))]
-    SendPrivmsg {
-        source: twitchchat::Error,
-        channel: String,
-    },
+    #[snafu(display("Running blocking task: {}", source))]
+    Blocking { source: tokio::task::JoinError },
+}
+
+impl Error {
+    /// Whether retrying the delivery cannot help, for example because the voicemail was deleted.
+    fn is_permanent(&self) -> bool {
+        matches!(
+            self,
+            Error::GetScheduled
+                | Error::VoicemailNotFound { .. }
+                | Error::ChannelNotFound { .. }
+                | Error::UserNotFound { .. }
+        )
+    }
 }
 
 type Result<T> = std::result::Result<T, Error>;
 
+/// How often a failed delivery is retried.
+const MAX_RETRIES: u32 = 5;
+
+/// How long to wait before the first retry. The delay doubles with every retry.
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Voicemails delivered later than this after their schedule get the late note.
+const LATE_AFTER: i64 = 60;
+
+/// The default of `voicemail.late_note`. `{late}` is replaced by how late the voicemail is.
+const LATE_NOTE: &str = "sent {late} late";
+
 #[derive(Clone)]
 pub struct Scheduler {
     queue: DelayQueue<i32>,
     receiver: Receiver<i32>,
+    /// Handles of the queued voicemails by id. They are needed to cancel voicemails.
+    handles: Arc<Mutex<HashMap<i32, DelayHandle>>>,
+    /// Failed deliveries by id.
+    retries: Arc<Mutex<HashMap<i32, u32>>>,
 }
 
 impl Scheduler {
     pub fn new() -> Self {
         // create a queue with maximum size
         let (queue, receiver) = delay_queue(100_000_000);
-        Self { queue, receiver }
+        Self {
+            queue,
+            receiver,
+            handles: Arc::new(Mutex::new(HashMap::new())),
+            retries: Arc::new(Mutex::new(HashMap::new())),
+        }
     }
 
-    pub async fn run(&self, context: Arc<BotContext>) -> Result<()> {
+    /// Deliver voicemails when they are due. Failed deliveries are retried.
+    pub async fn run(&self, context: Arc<BotContext>) {
         debug!("starting scheduler loop");
 
         loop {
             if let Some(id) = self.receiver.receive().await {
-                self.show(id, context.clone()).await?;
+                match self.show(id, context.clone()).await {
+                    Ok(()) => {
+                        self.retries.lock().unwrap().remove(&id);
+                    }
+                    Err(err) => self.retry(id, err),
+                }
             }
         }
     }
 
+    /// Queue a voicemail for its schedule. Overdue voicemails are delivered right away.
     pub fn schedule(&self, voicemail: Voicemail) -> Result<()> {
         trace!("scheduling voicemail (id: {})", voicemail.id);
 
-        self.queue.insert(
+        let handle = self.queue.insert(
             voicemail.id,
+            // the difference is negative if the voicemail is overdue
             (voicemail.scheduled.context(GetScheduled)? - Utc::now().naive_utc())
                 .to_std()
-                .context(ConvertToStdDuration)?,
+                .unwrap_or_default(),
         );
+        self.insert_handle(voicemail.id, handle);
 
         Ok(())
     }
 
+    /// Deliver a voicemail as soon as possible, for example after its channel was resumed.
+    pub fn schedule_now(&self, voicemail: &Voicemail) {
+        trace!("scheduling voicemail now (id: {})", voicemail.id);
+
+        let handle = self.queue.insert(voicemail.id, Duration::from_secs(0));
+        self.insert_handle(voicemail.id, handle);
+    }
+
+    /// Remove a voicemail from the queue. Returns `false` if it was not queued.
+    pub async fn cancel(&self, id: i32) -> bool {
+        trace!("cancelling voicemail (id: {})", id);
+
+        let handle = self.handles.lock().unwrap().remove(&id);
+        self.retries.lock().unwrap().remove(&id);
+
+        match handle {
+            Some(handle) => handle.cancel().await.is_ok(),
+            None => false,
+        }
+    }
+
+    fn insert_handle(&self, id: i32, handle: DelayHandle) {
+        self.handles.lock().unwrap().insert(id, handle);
+    }
+
+    /// Queue a voicemail again after its delivery failed. It stays active if it is given up and
+    /// is scheduled again on the next start.
+    fn retry(&self, id: i32, err: Error) {
+        if err.is_permanent() {
+            error!("Could not deliver voicemail (id: {}): {}", id, err);
+            self.retries.lock().unwrap().remove(&id);
+            return;
+        }
+
+        let attempt = {
+            let mut retries = self.retries.lock().unwrap();
+            let attempt = retries.entry(id).or_insert(0);
+            *attempt += 1;
+            *attempt
+        };
+
+        if attempt > MAX_RETRIES {
+            error!(
+                "Giving up on voicemail after {} retries (id: {}): {}",
+                MAX_RETRIES, id, err
+            );
+            self.retries.lock().unwrap().remove(&id);
+            return;
+        }
+
+        let delay = RETRY_DELAY * 2u32.pow(attempt - 1);
+
+        warn!(
+            "Could not deliver voicemail, retrying in {} (id: {}, attempt: {}): {}",
+            format_duration(delay),
+            id,
+            attempt,
+            err
+        );
+
+        let handle = self.queue.insert(id, delay);
+        self.insert_handle(id, handle);
+    }
+
     async fn show(&self, id: i32, context: Arc<BotContext>) -> Result<()> {
         trace!("showing voicemail (id: {})", id);
 
-        let conn = &context.conn();
-
-        let v = Voicemail::by_id(conn, id)
-            .context(GetVoicemail)?
-            .context(VoicemailNotFound { id })?;
-
-        // disable voicemail
-        v.set_active(conn, false).context(DisableVoicemail)?;
-
-        let channel = Channel::by_id(conn, v.channel_id)
-            .context(GetChannel)?
-            .context(ChannelNotFound { id: v.channel_id })?;
-        let channel_name = channel.name(conn).context(GetChannelName)?;
-
-        let receiver = User::by_id(conn, v.receiver_id)
-            .context(GetUser)?
-            .context(UserNotFound { id: v.receiver_id })?;
-
-        context
-            .twitchbot()
-            .writer()
-            .privmsg(
-                &channel_name,
-                &format!(
-                    "{}, one message for you: {}",
-                    receiver.display_name_or_name(),
-                    &v.to_string(conn)
-                ),
-            )
-            .await
-            .context(SendPrivmsg {
-                channel: &channel_name,
-            })
+        self.handles.lock().unwrap().remove(&id);
+
+        // database queries are blocking
+        let delivery = {
+            let context = context.clone();
+            blocking(move || prepare(id, &context))
+                .await
+                .context(Blocking)??
+        };
+
+        match delivery {
+            Some(Delivery::Whisper { user, text }) => context.twitchbot().whisper(&user, &text),
+            Some(Delivery::Say { channel, text }) => context.twitchbot().say(&channel, &text),
+            None => {}
+        }
+
+        Ok(())
+    }
+}
+
+/// How a voicemail is delivered.
+enum Delivery {
+    Whisper { user: String, text: String },
+    Say { channel: String, text: String },
+}
+
+/// Get the voicemail `id` ready for delivery and disable it. Returns `None` if it is not delivered
+/// now.
+fn prepare(id: i32, context: &BotContext) -> Result<Option<Delivery>> {
+    let conn = &context.pool().get().context(GetConnection)?;
+
+    let v = Voicemail::by_id(conn, id)
+        .context(GetVoicemail)?
+        .context(VoicemailNotFound { id })?;
+
+    // the voicemail was cancelled or delivered already
+    if !v.active {
+        debug!("skipping inactive voicemail (id: {})", id);
+        return Ok(None);
+    }
+
+    let channel = Channel::by_id(conn, v.channel_id)
+        .context(GetChannel)?
+        .context(ChannelNotFound { id: v.channel_id })?;
+
+    // the voicemail stays active and is delivered when the channel is resumed
+    if channel.paused {
+        debug!("holding voicemail until channel is resumed (id: {})", id);
+        return Ok(None);
     }
+
+    let channel_name = channel.name(conn).context(GetChannelName)?;
+
+    let receiver = User::by_id(conn, v.receiver_id)
+        .context(GetUser)?
+        .context(UserNotFound { id: v.receiver_id })?;
+
+    let mut text = format!(
+        "{}, one message for you: {}",
+        receiver.display_name_or_name(),
+        v.to_string(conn)
+    );
+
+    if let Some(note) = late_note(context, &v) {
+        text = format!("{} ({})", text, note);
+    }
+
+    let whisper = UserSettings::of(conn, &receiver)
+        .context(GetSettings)?
+        .whisper_voicemails();
+
+    // disable the voicemail last so that failed deliveries can be retried
+    v.set_active(conn, false).context(DisableVoicemail)?;
+
+    Ok(Some(if whisper {
+        Delivery::Whisper {
+            user: receiver.name,
+            text,
+        }
+    } else {
+        Delivery::Say {
+            channel: channel_name,
+            text,
+        }
+    }))
+}
+
+/// Get the note for voicemails delivered late, for example because the bot was offline. Returns
+/// `None` if the voicemail is on time or the note is empty.
+fn late_note(context: &BotContext, voicemail: &Voicemail) -> Option<String> {
+    let late = Utc::now().naive_utc() - voicemail.scheduled?;
+
+    let note = context
+        .config()
+        .get_str("voicemail.late_note")
+        .unwrap_or_else(|_| String::from(LATE_NOTE));
+
+    format_late_note(&note, late)
+}
+
+/// Fill in how late a voicemail is. Returns `None` if it is on time or the note is empty.
+fn format_late_note(note: &str, late: chrono::Duration) -> Option<String> {
+    if late.num_seconds() < LATE_AFTER || note.is_empty() {
+        return None;
+    }
+
+    Some(note.replace(
+        "{late}",
+        &format_duration(truncate_duration(late.to_std().unwrap_or_default())).to_string(),
+    ))
 }
 
 impl Default for Scheduler {
@@ -128,3 +317,25 @@ impl Default for Scheduler {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_late_note() {
+        assert_eq!(
+            format_late_note(LATE_NOTE, chrono::Duration::minutes(90)),
+            Some(String::from("sent 1h 30m late"))
+        );
+        assert_eq!(
+            format_late_note("{late} too late", chrono::Duration::seconds(61)),
+            Some(String::from("1m 1s too late"))
+        );
+        assert_eq!(
+            format_late_note(LATE_NOTE, chrono::Duration::seconds(59)),
+            None
+        );
+        assert_eq!(format_late_note("", chrono::Duration::hours(2)), None);
+    }
+}